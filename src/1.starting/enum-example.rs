@@ -1,25 +1,18 @@
-// Define a tuple struct
-// Set the Debug flag so we can check the data in the output
-#[derive(Debug)]
-struct KeyPress(String, char);
+// The KeyPress tuple struct, the MouseClick classic struct and the WebEvent enum
+// live in the events module so other programs can use them too
+mod events;
 
-// Define a classic struct
-#[derive(Debug)]
-struct MouseClick { x: i64, y: i64 }
-
-// Define the WebEvent enum variants to use the data from the structs
-// and a boolean type for the page Load variant
-#[derive(Debug)]
-enum WebEvent { WELoad(bool), WEClick(MouseClick), WEKeys(KeyPress) }
+use events::keys::KeyChord;
+use events::{Key, KeyPress, Modifiers, MouseButton, MouseClick, TargetId, TimedEvent, WebEvent};
 
 fn main() {
   // Instantiate a MouseClick struct and bind the coordinate values
-  let click = MouseClick { x: 100, y: 250 };
+  let click = MouseClick { x: 100, y: 250, button: MouseButton::Left, modifiers: Modifiers::NONE };
   println!("Mouse click location: {}, {}", click.x, click.y);
       
   // Instantiate a KeyPress tuple and bind the key values
-  let keys = KeyPress(String::from("Ctrl+"), 'N');
-  println!("\nKeys pressed: {}{}", keys.0, keys.1);
+  let keys = KeyPress(Modifiers::CTRL, Key::Char('N'));
+  println!("\nKeys pressed: {}", keys);
       
   // Instantiate WebEvent enum variants
   // Set the boolean page Load value to true
@@ -32,4 +25,13 @@ fn main() {
   // Print the values in the WebEvent enum variants
   // Use the {:#?} syntax to display the enum structure and data in a readable form
   println!("\nWebEvent enum structure: \n\n {:#?} \n\n {:#?} \n\n {:#?}", we_load, we_click, we_key);
-}
\ No newline at end of file
+
+  // Wrap an event with the time it happened and the element it happened on
+  let timed = TimedEvent::now(TargetId::new("#save-button"), WebEvent::WEClick(MouseClick::at(12, 40)));
+  println!("\nTimed event: \n\n {:#?}", timed);
+
+  // Shortcuts can also be written as text and parsed into key presses
+  let save_as: KeyPress = "shift+ctrl+s".parse().unwrap();
+  let comment: KeyChord = "Ctrl+K Ctrl+C".parse().unwrap();
+  println!("\nParsed shortcuts: {} and {}", save_as, comment);
+}
@@ -0,0 +1,81 @@
+// Search recorded WebEvent logs with queries like "click where x > 100 and y < 300".
+// See events/query.rs for everything a query can say.
+//
+// Build:  rustc event-query.rs
+// Usage:
+//   ./event-query filter <file> <query>              print every matching event
+//   ./event-query count <file> <query>               only say how many events match
+//   ./event-query group <file> <field> [query]       count matching events per value of a field
+//
+// Recordings made by event-replay work in either format. Quote the query so the shell
+// passes it as one argument:
+//   ./event-query filter session.jsonl "keys where key = 'N' and mods contains Ctrl"
+//   ./event-query group session.jsonl button "click where y < 300"
+
+mod events;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use events::query::{self, Field, Query};
+use events::replay;
+use events::TimedEvent;
+
+const USAGE: &str = "usage:
+  event-query filter <file> <query>
+  event-query count <file> <query>
+  event-query group <file> <field> [query]";
+
+// Parse the query first so a typo is reported before reading a large file
+fn matching(file: &str, query: &str) -> Result<Vec<TimedEvent>, String> {
+  let query = Query::parse(query).map_err(|error| format!("invalid query: {}", error))?;
+  let events = replay::load(Path::new(file)).map_err(|error| error.to_string())?;
+  Ok(events.into_iter().filter(|event| query.matches(event)).collect())
+}
+
+fn filter(file: &str, query: &str) -> Result<String, String> {
+  let events = matching(file, query)?;
+  for event in &events {
+    println!("{:>13}  {:<12} {}", event.timestamp.as_millis(), event.target.as_str(), event.event);
+  }
+  Ok(format!("{} matching events", events.len()))
+}
+
+fn count(file: &str, query: &str) -> Result<String, String> {
+  Ok(matching(file, query)?.len().to_string())
+}
+
+fn group(file: &str, field: &str, query: &str) -> Result<String, String> {
+  let field = Field::from_name(field).ok_or(format!("unknown field '{}'", field))?;
+  let events = matching(file, query)?;
+  let groups = query::group_by(&events, field);
+  let width = groups.iter().map(|(value, _)| value.len()).max().unwrap_or(0);
+  for (value, count) in &groups {
+    println!("{:<width$}  {}", value, count, width = width);
+  }
+  let missing = events.len() - groups.iter().map(|(_, count)| count).sum::<usize>();
+  Ok(format!("{} events in {} groups, {} without a {}", events.len(), groups.len(), missing, field))
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+  let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+  match args.as_slice() {
+    ["filter", file, query] => filter(file, query),
+    ["count", file, query] => count(file, query),
+    ["group", file, field] => group(file, field, "*"),
+    ["group", file, field, query] => group(file, field, query),
+    _ => Err(String::from(USAGE)),
+  }
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  match run(&args) {
+    Ok(summary) => println!("{}", summary),
+    Err(message) => {
+      eprintln!("{}", message);
+      process::exit(1);
+    },
+  }
+}
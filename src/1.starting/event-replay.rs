@@ -0,0 +1,136 @@
+// Record WebEvent streams and play them back, so a bug a tester hit can be reproduced.
+//
+// Build:  rustc event-replay.rs
+// Usage:
+//   ./event-replay record <file>              type events, one per line, Ctrl+D to stop:
+//                                               load true
+//                                               click 100 250 [left|middle|right|back|forward]
+//                                               keys Ctrl+Shift+N
+//   ./event-replay play <file> [speed]        replay at the original pace (speed 2 = twice as fast)
+//   ./event-replay play <file> fast           replay without waiting between events
+//   ./event-replay convert <from> <to>        files ending in .bin are binary, others JSON Lines
+//   ./event-replay generate <file> <count> [seed]   write made-up events, the same seed gives the same events
+
+mod events;
+
+use std::env;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process;
+
+use events::replay::{self, Pacing, Recorder};
+use events::synth::{EventGenerator, GeneratorConfig};
+use events::{KeyPress, MouseButton, MouseClick, TargetId, WebEvent};
+
+const USAGE: &str = "usage:
+  event-replay record <file>
+  event-replay play <file> [speed|fast]
+  event-replay convert <from> <to>
+  event-replay generate <file> <count> [seed]";
+
+fn parse_button(name: &str) -> Result<MouseButton, String> {
+  match name {
+    "left" => Ok(MouseButton::Left),
+    "middle" => Ok(MouseButton::Middle),
+    "right" => Ok(MouseButton::Right),
+    "back" => Ok(MouseButton::Back),
+    "forward" => Ok(MouseButton::Forward),
+    _ => Err(format!("unknown mouse button '{}'", name)),
+  }
+}
+
+fn parse_event(line: &str) -> Result<WebEvent, String> {
+  let words: Vec<&str> = line.split_whitespace().collect();
+  match words.as_slice() {
+    ["load", loaded] => loaded.parse().map(WebEvent::WELoad).map_err(|_| format!("'{}' is not true or false", loaded)),
+    ["click", x, y, rest @ ..] if rest.len() <= 1 => {
+      let mut click = MouseClick::at(
+        x.parse().map_err(|_| format!("'{}' is not a coordinate", x))?,
+        y.parse().map_err(|_| format!("'{}' is not a coordinate", y))?,
+      );
+      if let Some(button) = rest.first() {
+        click.button = parse_button(button)?;
+      }
+      Ok(WebEvent::WEClick(click))
+    },
+    ["keys", keys] => keys.parse::<KeyPress>().map(WebEvent::WEKeys).map_err(|error| error.to_string()),
+    _ => Err(String::from("expected 'load <bool>', 'click <x> <y> [button]' or 'keys <shortcut>'")),
+  }
+}
+
+fn record(path: &Path) -> Result<String, String> {
+  let mut recorder = Recorder::create(path).map_err(|error| error.to_string())?;
+  for line in io::stdin().lock().lines() {
+    let line = line.map_err(|error| error.to_string())?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    match parse_event(&line) {
+      Ok(event) => recorder.record_now(TargetId::new("stdin"), event).map_err(|error| error.to_string())?,
+      Err(message) => eprintln!("skipped: {}", message),
+    }
+  }
+  let recorded = recorder.recorded();
+  recorder.finish().map_err(|error| error.to_string())?;
+  Ok(format!("recorded {} events to {}", recorded, path.display()))
+}
+
+fn play(path: &Path, pacing: Pacing) -> Result<String, String> {
+  let events = replay::load(path).map_err(|error| error.to_string())?;
+  let first = events.first().map(|event| event.timestamp);
+  let report = replay::replay(&events, pacing, |event| {
+    let offset = first.map(|first| event.timestamp.duration_since(first)).unwrap_or_default();
+    println!("+{:>8}ms  {:<12} {}", offset.as_millis(), event.target.as_str(), event.event);
+  });
+  Ok(format!("replayed {} events in {:?}", report.replayed, report.elapsed))
+}
+
+fn convert(from: &Path, to: &Path) -> Result<String, String> {
+  let events = replay::load(from).map_err(|error| error.to_string())?;
+  let mut recorder = Recorder::create(to).map_err(|error| error.to_string())?;
+  for event in &events {
+    recorder.record(event).map_err(|error| error.to_string())?;
+  }
+  recorder.finish().map_err(|error| error.to_string())?;
+  Ok(format!("converted {} events", events.len()))
+}
+
+fn generate(path: &Path, count: &str, seed: &str) -> Result<String, String> {
+  let count: usize = count.parse().map_err(|_| format!("'{}' is not a number of events", count))?;
+  let seed: u64 = seed.parse().map_err(|_| format!("'{}' is not a seed", seed))?;
+  let generator = EventGenerator::new(GeneratorConfig::default(), seed).map_err(|error| error.to_string())?;
+  let mut recorder = Recorder::create(path).map_err(|error| error.to_string())?;
+  for event in generator.take(count) {
+    recorder.record(&event).map_err(|error| error.to_string())?;
+  }
+  recorder.finish().map_err(|error| error.to_string())?;
+  Ok(format!("generated {} events with seed {}", count, seed))
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+  let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+  match args.as_slice() {
+    ["record", file] => record(Path::new(file)),
+    ["play", file] => play(Path::new(file), Pacing::Original { speed: 1.0 }),
+    ["play", file, "fast"] => play(Path::new(file), Pacing::Unpaced),
+    ["play", file, speed] => match speed.parse::<f64>().ok().and_then(Pacing::original) {
+      Some(pacing) => play(Path::new(file), pacing),
+      None => Err(format!("'{}' is not a speed between {} and {}", speed, Pacing::MIN_SPEED, Pacing::MAX_SPEED)),
+    },
+    ["convert", from, to] => convert(Path::new(from), Path::new(to)),
+    ["generate", file, count] => generate(Path::new(file), count, "0"),
+    ["generate", file, count, seed] => generate(Path::new(file), count, seed),
+    _ => Err(String::from(USAGE)),
+  }
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  match run(&args) {
+    Ok(summary) => println!("{}", summary),
+    Err(message) => {
+      eprintln!("{}", message);
+      process::exit(1);
+    },
+  }
+}
@@ -0,0 +1,260 @@
+// ======================== EVENT BUS ========================
+// variables.rs notes that a function taking a WebEvent must accept every variant. An event
+// bus flips that around: handlers say which events they care about (only clicks, only keys,
+// or anything matching a predicate) and the bus only calls them for those.
+//
+//   > handlers with a higher priority run first, equal priorities run in the order they
+//     were added
+//   > a handler returns Propagation::Stop to keep the event from reaching later handlers
+//   > subscribing returns a SubscriptionId that can be passed to unsubscribe
+//   > ThreadedBus moves the bus to its own thread and feeds it through a channel, so any
+//     thread can publish events. A handler that panics there skips the rest of that event
+//     but doesn't take the thread down with it.
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+
+use super::{EventKind, KeyPress, MouseClick, TimedEvent, WebEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation { Continue, Stop }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubscriptionId(u64);
+
+// Which events a handler wants to receive
+pub enum EventFilter {
+  Any,
+  Kind(EventKind),
+  Predicate(Box<dyn Fn(&TimedEvent) -> bool + Send>),
+}
+
+impl EventFilter {
+  pub fn matches(&self, event: &TimedEvent) -> bool {
+    match self {
+      EventFilter::Any => true,
+      EventFilter::Kind(kind) => event.event.kind() == *kind,
+      EventFilter::Predicate(predicate) => predicate(event),
+    }
+  }
+}
+
+pub type Handler = Box<dyn FnMut(&TimedEvent) -> Propagation + Send>;
+
+struct Subscription {
+  id: SubscriptionId,
+  priority: i32,
+  filter: EventFilter,
+  handler: Handler,
+}
+
+// What happened to one published event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Delivery {
+  pub handled_by: usize,
+  pub stopped: bool,
+}
+
+#[derive(Default)]
+pub struct EventBus {
+  // Kept sorted by priority (highest first), then by id (oldest first)
+  subscriptions: Vec<Subscription>,
+  next_id: u64,
+}
+
+impl EventBus {
+  pub fn new() -> EventBus {
+    EventBus::default()
+  }
+
+  pub fn subscribe<F>(&mut self, filter: EventFilter, priority: i32, handler: F) -> SubscriptionId
+  where
+    F: FnMut(&TimedEvent) -> Propagation + Send + 'static,
+  {
+    let id = SubscriptionId(self.next_id);
+    self.next_id += 1;
+    // Insert after every subscription with the same or a higher priority
+    let position = self.subscriptions.iter().position(|existing| existing.priority < priority)
+      .unwrap_or(self.subscriptions.len());
+    self.subscriptions.insert(position, Subscription { id, priority, filter, handler: Box::new(handler) });
+    id
+  }
+
+  // Only WELoad events, with the loaded flag already taken out
+  pub fn on_load<F>(&mut self, priority: i32, mut handler: F) -> SubscriptionId
+  where
+    F: FnMut(bool, &TimedEvent) -> Propagation + Send + 'static,
+  {
+    self.subscribe(EventFilter::Kind(EventKind::Load), priority, move |event| match event.event {
+      WebEvent::WELoad(loaded) => handler(loaded, event),
+      _ => Propagation::Continue,
+    })
+  }
+
+  // Only WEClick events, with the MouseClick already taken out
+  pub fn on_click<F>(&mut self, priority: i32, mut handler: F) -> SubscriptionId
+  where
+    F: FnMut(&MouseClick, &TimedEvent) -> Propagation + Send + 'static,
+  {
+    self.subscribe(EventFilter::Kind(EventKind::Click), priority, move |event| match &event.event {
+      WebEvent::WEClick(click) => handler(click, event),
+      _ => Propagation::Continue,
+    })
+  }
+
+  // Only WEKeys events, with the KeyPress already taken out
+  pub fn on_keys<F>(&mut self, priority: i32, mut handler: F) -> SubscriptionId
+  where
+    F: FnMut(&KeyPress, &TimedEvent) -> Propagation + Send + 'static,
+  {
+    self.subscribe(EventFilter::Kind(EventKind::Keys), priority, move |event| match &event.event {
+      WebEvent::WEKeys(keys) => handler(keys, event),
+      _ => Propagation::Continue,
+    })
+  }
+
+  // Returns false when the id was already removed
+  pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+    let before = self.subscriptions.len();
+    self.subscriptions.retain(|subscription| subscription.id != id);
+    self.subscriptions.len() != before
+  }
+
+  pub fn len(&self) -> usize {
+    self.subscriptions.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.subscriptions.is_empty()
+  }
+
+  pub fn publish(&mut self, event: &TimedEvent) -> Delivery {
+    let mut delivery = Delivery::default();
+    for subscription in self.subscriptions.iter_mut() {
+      if !subscription.filter.matches(event) {
+        continue;
+      }
+      delivery.handled_by += 1;
+      if (subscription.handler)(event) == Propagation::Stop {
+        delivery.stopped = true;
+        break;
+      }
+    }
+    delivery
+  }
+}
+
+// ======================== THREADED BUS ========================
+
+enum Message {
+  Publish(TimedEvent),
+  Subscribe { filter: EventFilter, priority: i32, handler: Handler, reply: Sender<SubscriptionId> },
+  Unsubscribe { id: SubscriptionId, reply: Sender<bool> },
+  Shutdown,
+}
+
+// The bus thread is gone, so nothing was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusStopped;
+
+impl fmt::Display for BusStopped {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "the event bus thread has stopped")
+  }
+}
+
+impl std::error::Error for BusStopped {}
+
+// A cheap, clonable handle that any thread can use to send events to a ThreadedBus
+#[derive(Clone)]
+pub struct Publisher {
+  sender: Sender<Message>,
+  // Set by shutdown. Publishers send while holding the read lock, so every event is either
+  // queued before Message::Shutdown or refused, never queued behind it and lost.
+  stopping: Arc<RwLock<bool>>,
+}
+
+impl Publisher {
+  // Returns the event back if the bus has shut down or is shutting down
+  pub fn publish(&self, event: TimedEvent) -> Result<(), TimedEvent> {
+    let stopping = self.stopping.read().unwrap();
+    if *stopping {
+      return Err(event);
+    }
+    self.sender.send(Message::Publish(event)).map_err(|error| match error.0 {
+      Message::Publish(event) => event,
+      _ => unreachable!("only Publish messages are sent from here"),
+    })
+  }
+}
+
+// An EventBus running on its own thread. Events are handled in the order they arrive.
+pub struct ThreadedBus {
+  sender: Sender<Message>,
+  stopping: Arc<RwLock<bool>>,
+  worker: JoinHandle<EventBus>,
+}
+
+impl ThreadedBus {
+  pub fn spawn(bus: EventBus) -> ThreadedBus {
+    let (sender, receiver) = mpsc::channel();
+    let worker = thread::spawn(move || run_bus(bus, receiver));
+    ThreadedBus { sender, stopping: Arc::new(RwLock::new(false)), worker }
+  }
+
+  pub fn publisher(&self) -> Publisher {
+    Publisher { sender: self.sender.clone(), stopping: Arc::clone(&self.stopping) }
+  }
+
+  // Returns the event back if the bus thread has stopped
+  pub fn publish(&self, event: TimedEvent) -> Result<(), TimedEvent> {
+    self.publisher().publish(event)
+  }
+
+  pub fn subscribe<F>(&self, filter: EventFilter, priority: i32, handler: F) -> Result<SubscriptionId, BusStopped>
+  where
+    F: FnMut(&TimedEvent) -> Propagation + Send + 'static,
+  {
+    let (reply, answer) = mpsc::channel();
+    self.sender.send(Message::Subscribe { filter, priority, handler: Box::new(handler), reply })
+      .map_err(|_| BusStopped)?;
+    answer.recv().map_err(|_| BusStopped)
+  }
+
+  // Ok(false) when the id was already removed
+  pub fn unsubscribe(&self, id: SubscriptionId) -> Result<bool, BusStopped> {
+    let (reply, answer) = mpsc::channel();
+    self.sender.send(Message::Unsubscribe { id, reply }).map_err(|_| BusStopped)?;
+    answer.recv().map_err(|_| BusStopped)
+  }
+
+  // Handle every event sent so far, stop the thread and give the bus back.
+  // Publishers still alive after this get their events returned as errors.
+  pub fn shutdown(self) -> thread::Result<EventBus> {
+    *self.stopping.write().unwrap() = true;
+    let _ = self.sender.send(Message::Shutdown);
+    self.worker.join()
+  }
+}
+
+fn run_bus(mut bus: EventBus, receiver: Receiver<Message>) -> EventBus {
+  for message in receiver {
+    match message {
+      Message::Publish(event) => {
+        // The panic is still reported by the panic hook; the bus moves on to the next event
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| bus.publish(&event)));
+      },
+      Message::Subscribe { filter, priority, handler, reply } => {
+        let _ = reply.send(bus.subscribe(filter, priority, handler));
+      },
+      Message::Unsubscribe { id, reply } => {
+        let _ = reply.send(bus.unsubscribe(id));
+      },
+      Message::Shutdown => break,
+    }
+  }
+  bus
+}
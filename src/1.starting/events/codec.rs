@@ -0,0 +1,473 @@
+// ======================== EVENT CODEC ========================
+// `{:#?}` is nice to read but can't be parsed back. This module saves TimedEvent values in
+// two formats that can:
+//
+// JSON Lines, one event per line, tagged by variant:
+//   {"v":1,"ts":1700000000000,"target":"#page","type":"load","loaded":true}
+//   {"v":1,"ts":1700000000250,"target":"#save","type":"click","x":100,"y":250,"button":"left","mods":[]}
+//   {"v":1,"ts":1700000000900,"target":"#editor","type":"keys","key":"N","mods":["ctrl"]}
+//
+// A compact binary file:
+//   b"WEVT" | version: u8 | records...
+//   record  = length: u32 LE | timestamp: u64 LE | target length: u16 LE | target bytes
+//             | tag: u8 | variant data
+//
+// Every JSON line and the binary header carry a format version. Readers dispatch on it, so
+// a newer program can keep reading old recordings. Fields missing from a JSON line use their
+// default, unknown JSON fields are ignored, and extra bytes at the end of a binary record are
+// skipped, which lets a future version add data without breaking this reader.
+
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use super::json::{self, JsonValue};
+use super::{Key, KeyPress, Modifiers, MouseButton, MouseClick, NamedKey, TargetId, TimedEvent, Timestamp, WebEvent};
+
+pub const FORMAT_VERSION: u32 = 1;
+pub const BINARY_MAGIC: &[u8; 4] = b"WEVT";
+
+#[derive(Debug)]
+pub enum CodecError {
+  Io(io::Error),
+  Syntax(String),
+  MissingField(&'static str),
+  InvalidField { field: &'static str, value: String },
+  UnsupportedVersion(u32),
+  BadMagic,
+  Truncated { offset: usize },
+  // A binary record stores the target's length in a u16
+  TargetTooLong { length: usize },
+  // Where in a file the wrapped error happened
+  AtLine { line: usize, error: Box<CodecError> },
+  AtRecord { record: usize, error: Box<CodecError> },
+}
+
+impl fmt::Display for CodecError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CodecError::Io(error) => write!(f, "{}", error),
+      CodecError::Syntax(message) => write!(f, "invalid JSON: {}", message),
+      CodecError::MissingField(field) => write!(f, "missing field '{}'", field),
+      CodecError::InvalidField { field, value } => write!(f, "invalid value {} for field '{}'", value, field),
+      CodecError::UnsupportedVersion(version) => {
+        write!(f, "format version {} is newer than this program understands (up to {})", version, FORMAT_VERSION)
+      },
+      CodecError::BadMagic => write!(f, "not a WebEvent recording (missing the WEVT header)"),
+      CodecError::Truncated { offset } => write!(f, "data ends in the middle of a record at byte {}", offset),
+      CodecError::TargetTooLong { length } => {
+        write!(f, "target is {} bytes long, a binary record holds at most {}", length, u16::MAX)
+      },
+      CodecError::AtLine { line, error } => write!(f, "line {}: {}", line, error),
+      CodecError::AtRecord { record, error } => write!(f, "record {}: {}", record, error),
+    }
+  }
+}
+
+impl std::error::Error for CodecError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      CodecError::Io(error) => Some(error),
+      CodecError::AtLine { error, .. } | CodecError::AtRecord { error, .. } => Some(error.as_ref()),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for CodecError {
+  fn from(error: io::Error) -> CodecError {
+    CodecError::Io(error)
+  }
+}
+
+// ======================== SHARED NAMES ========================
+
+const MODIFIER_NAMES: [&str; 4] = ["ctrl", "alt", "shift", "meta"];
+const BUTTONS: [(MouseButton, &str); 5] = [
+  (MouseButton::Left, "left"),
+  (MouseButton::Middle, "middle"),
+  (MouseButton::Right, "right"),
+  (MouseButton::Back, "back"),
+  (MouseButton::Forward, "forward"),
+];
+// The position in this list is the key's code in the binary format, so only append to it
+const NAMED_KEYS: [NamedKey; 15] = [
+  NamedKey::Enter, NamedKey::Escape, NamedKey::Tab, NamedKey::Space, NamedKey::Backspace,
+  NamedKey::Delete, NamedKey::Insert, NamedKey::Home, NamedKey::End, NamedKey::PageUp,
+  NamedKey::PageDown, NamedKey::ArrowUp, NamedKey::ArrowDown, NamedKey::ArrowLeft, NamedKey::ArrowRight,
+];
+const FUNCTION_KEY_CODE: u8 = 255;
+
+fn modifier_flags(modifiers: Modifiers) -> [bool; 4] {
+  [modifiers.ctrl, modifiers.alt, modifiers.shift, modifiers.meta]
+}
+
+fn modifiers_from_flags(flags: [bool; 4]) -> Modifiers {
+  Modifiers { ctrl: flags[0], alt: flags[1], shift: flags[2], meta: flags[3] }
+}
+
+fn modifier_bits(modifiers: Modifiers) -> u8 {
+  modifier_flags(modifiers).iter().enumerate().fold(0, |bits, (index, held)| bits | ((*held as u8) << index))
+}
+
+fn modifiers_from_bits(bits: u8) -> Modifiers {
+  modifiers_from_flags([bits & 1 != 0, bits & 2 != 0, bits & 4 != 0, bits & 8 != 0])
+}
+
+fn button_name(button: MouseButton) -> &'static str {
+  BUTTONS.iter().find(|(candidate, _)| *candidate == button).map(|(_, name)| *name).unwrap_or("left")
+}
+
+// ======================== JSON LINES ========================
+
+fn key_to_json(key: Key) -> String {
+  match key {
+    // A single character is always Key::Char, so "F" and "F4" can't be confused
+    Key::Char(c) => c.to_string(),
+    Key::Named(named) => named.to_string(),
+  }
+}
+
+fn key_from_json(text: &str) -> Result<Key, CodecError> {
+  let mut chars = text.chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) => Ok(Key::Char(c)),
+    _ => text.parse::<NamedKey>().map(Key::Named).map_err(|_| CodecError::InvalidField {
+      field: "key",
+      value: format!("{:?}", text),
+    }),
+  }
+}
+
+pub fn event_to_json(event: &TimedEvent) -> JsonValue {
+  let number = |value: String| JsonValue::Number(value);
+  let mut fields = vec![
+    (String::from("v"), number(FORMAT_VERSION.to_string())),
+    (String::from("ts"), number(event.timestamp.as_millis().to_string())),
+    (String::from("target"), JsonValue::String(event.target.as_str().to_string())),
+  ];
+  let mods = |modifiers: Modifiers| {
+    let held = MODIFIER_NAMES.iter().zip(modifier_flags(modifiers)).filter(|(_, held)| *held);
+    JsonValue::Array(held.map(|(name, _)| JsonValue::String(name.to_string())).collect())
+  };
+  match event.event {
+    WebEvent::WELoad(loaded) => {
+      fields.push((String::from("type"), JsonValue::String(String::from("load"))));
+      fields.push((String::from("loaded"), JsonValue::Bool(loaded)));
+    },
+    WebEvent::WEClick(click) => {
+      fields.push((String::from("type"), JsonValue::String(String::from("click"))));
+      fields.push((String::from("x"), number(click.x.to_string())));
+      fields.push((String::from("y"), number(click.y.to_string())));
+      fields.push((String::from("button"), JsonValue::String(button_name(click.button).to_string())));
+      fields.push((String::from("mods"), mods(click.modifiers)));
+    },
+    WebEvent::WEKeys(keys) => {
+      fields.push((String::from("type"), JsonValue::String(String::from("keys"))));
+      fields.push((String::from("key"), JsonValue::String(key_to_json(keys.key()))));
+      fields.push((String::from("mods"), mods(keys.modifiers())));
+    },
+  }
+  JsonValue::Object(fields)
+}
+
+pub fn to_json_line(event: &TimedEvent) -> String {
+  event_to_json(event).to_json()
+}
+
+fn required<'a>(object: &'a JsonValue, field: &'static str) -> Result<&'a JsonValue, CodecError> {
+  object.get(field).ok_or(CodecError::MissingField(field))
+}
+
+fn invalid(field: &'static str, value: &JsonValue) -> CodecError {
+  CodecError::InvalidField { field, value: value.to_json() }
+}
+
+fn modifiers_from_json(object: &JsonValue) -> Result<Modifiers, CodecError> {
+  let list = match object.get("mods") {
+    Some(list) => list,
+    None => return Ok(Modifiers::NONE),
+  };
+  let names = list.as_array().ok_or_else(|| invalid("mods", list))?;
+  let mut flags = [false; 4];
+  for name in names {
+    let index = name.as_str().and_then(|text| MODIFIER_NAMES.iter().position(|known| *known == text));
+    flags[index.ok_or_else(|| invalid("mods", name))?] = true;
+  }
+  Ok(modifiers_from_flags(flags))
+}
+
+// Version 1 is the format described at the top of this file
+fn event_from_json_v1(object: &JsonValue) -> Result<TimedEvent, CodecError> {
+  let ts = required(object, "ts")?;
+  let timestamp = Timestamp(ts.as_u64().ok_or_else(|| invalid("ts", ts))?);
+  let target = match object.get("target") {
+    Some(target) => TargetId::new(target.as_str().ok_or_else(|| invalid("target", target))?),
+    None => TargetId::default(),
+  };
+
+  let kind = required(object, "type")?;
+  let event = match kind.as_str() {
+    Some("load") => {
+      let loaded = required(object, "loaded")?;
+      WebEvent::WELoad(loaded.as_bool().ok_or_else(|| invalid("loaded", loaded))?)
+    },
+    Some("click") => {
+      let x = required(object, "x")?;
+      let y = required(object, "y")?;
+      let button = match object.get("button") {
+        Some(button) => {
+          let found = BUTTONS.iter().find(|(_, name)| Some(*name) == button.as_str());
+          found.map(|(button, _)| *button).ok_or_else(|| invalid("button", button))?
+        },
+        None => MouseButton::Left,
+      };
+      WebEvent::WEClick(MouseClick {
+        x: x.as_i64().ok_or_else(|| invalid("x", x))?,
+        y: y.as_i64().ok_or_else(|| invalid("y", y))?,
+        button,
+        modifiers: modifiers_from_json(object)?,
+      })
+    },
+    Some("keys") => {
+      let key = required(object, "key")?;
+      let key = key_from_json(key.as_str().ok_or_else(|| invalid("key", key))?)?;
+      WebEvent::WEKeys(KeyPress(modifiers_from_json(object)?, key))
+    },
+    _ => return Err(invalid("type", kind)),
+  };
+  Ok(TimedEvent { timestamp, target, event })
+}
+
+pub fn event_from_json(object: &JsonValue) -> Result<TimedEvent, CodecError> {
+  // Lines written before versioning was added would have no "v", treat them as version 1
+  let version = match object.get("v") {
+    Some(version) => version.as_u64().ok_or_else(|| invalid("v", version))?,
+    None => 1,
+  };
+  match version {
+    1 => event_from_json_v1(object),
+    _ => Err(CodecError::UnsupportedVersion(version.min(u32::MAX as u64) as u32)),
+  }
+}
+
+pub fn from_json_line(line: &str) -> Result<TimedEvent, CodecError> {
+  let object = json::parse(line).map_err(CodecError::Syntax)?;
+  event_from_json(&object)
+}
+
+pub fn write_json_lines<W: Write>(writer: &mut W, events: &[TimedEvent]) -> io::Result<()> {
+  for event in events {
+    writeln!(writer, "{}", to_json_line(event))?;
+  }
+  Ok(())
+}
+
+// Blank lines are skipped; errors say which line (counting from 1) was wrong
+pub fn read_json_lines<R: BufRead>(reader: R) -> Result<Vec<TimedEvent>, CodecError> {
+  let mut events = Vec::new();
+  for (index, line) in reader.lines().enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let event = from_json_line(&line).map_err(|error| CodecError::AtLine { line: index + 1, error: Box::new(error) })?;
+    events.push(event);
+  }
+  Ok(events)
+}
+
+// ======================== BINARY ========================
+
+const TAG_LOAD: u8 = 0;
+const TAG_CLICK: u8 = 1;
+const TAG_KEYS: u8 = 2;
+const KEY_CHAR: u8 = 0;
+const KEY_NAMED: u8 = 1;
+
+fn encode_record(event: &TimedEvent) -> Result<Vec<u8>, CodecError> {
+  let mut record = Vec::new();
+  record.extend_from_slice(&event.timestamp.as_millis().to_le_bytes());
+  // Cutting the target short could split a UTF-8 character, so a long one is refused instead
+  let target = event.target.as_str().as_bytes();
+  let target_length = u16::try_from(target.len()).map_err(|_| CodecError::TargetTooLong { length: target.len() })?;
+  record.extend_from_slice(&target_length.to_le_bytes());
+  record.extend_from_slice(target);
+  match event.event {
+    WebEvent::WELoad(loaded) => {
+      record.push(TAG_LOAD);
+      record.push(loaded as u8);
+    },
+    WebEvent::WEClick(click) => {
+      record.push(TAG_CLICK);
+      record.extend_from_slice(&click.x.to_le_bytes());
+      record.extend_from_slice(&click.y.to_le_bytes());
+      record.push(BUTTONS.iter().position(|(button, _)| *button == click.button).unwrap_or(0) as u8);
+      record.push(modifier_bits(click.modifiers));
+    },
+    WebEvent::WEKeys(keys) => {
+      record.push(TAG_KEYS);
+      record.push(modifier_bits(keys.modifiers()));
+      match keys.key() {
+        Key::Char(c) => {
+          record.push(KEY_CHAR);
+          record.extend_from_slice(&(c as u32).to_le_bytes());
+        },
+        // Decoding refuses anything but F1 to F24, so writing one would make a broken file
+        Key::Named(NamedKey::F(number)) => {
+          if NamedKey::function(number).is_none() {
+            return Err(CodecError::InvalidField { field: "key", value: format!("F{}", number) });
+          }
+          record.push(KEY_NAMED);
+          record.push(FUNCTION_KEY_CODE);
+          record.push(number);
+        },
+        Key::Named(named) => {
+          record.push(KEY_NAMED);
+          record.push(NAMED_KEYS.iter().position(|candidate| *candidate == named).unwrap_or(0) as u8);
+        },
+      }
+    },
+  }
+  Ok(record)
+}
+
+// The magic bytes and version that start every binary file
+pub fn binary_header() -> Vec<u8> {
+  let mut header = BINARY_MAGIC.to_vec();
+  header.push(FORMAT_VERSION as u8);
+  header
+}
+
+// One event with its length prefix, ready to append after the header
+pub fn encode_binary_record(event: &TimedEvent) -> Result<Vec<u8>, CodecError> {
+  let record = encode_record(event)?;
+  let mut framed = (record.len() as u32).to_le_bytes().to_vec();
+  framed.extend_from_slice(&record);
+  Ok(framed)
+}
+
+pub fn encode_binary(events: &[TimedEvent]) -> Result<Vec<u8>, CodecError> {
+  let mut bytes = binary_header();
+  for (index, event) in events.iter().enumerate() {
+    let record =
+      encode_binary_record(event).map_err(|error| CodecError::AtRecord { record: index + 1, error: Box::new(error) })?;
+    bytes.extend_from_slice(&record);
+  }
+  Ok(bytes)
+}
+
+// Reads fixed size values from a byte slice, remembering how far it got
+struct ByteReader<'a> {
+  bytes: &'a [u8],
+  offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+  fn take(&mut self, count: usize) -> Result<&'a [u8], CodecError> {
+    let end = self.offset + count;
+    if end > self.bytes.len() {
+      return Err(CodecError::Truncated { offset: self.offset });
+    }
+    let taken = &self.bytes[self.offset..end];
+    self.offset = end;
+    Ok(taken)
+  }
+
+  fn array<const N: usize>(&mut self) -> Result<[u8; N], CodecError> {
+    let mut array = [0; N];
+    array.copy_from_slice(self.take(N)?);
+    Ok(array)
+  }
+
+  fn u8(&mut self) -> Result<u8, CodecError> {
+    Ok(self.take(1)?[0])
+  }
+}
+
+fn decode_record_v1(record: &[u8]) -> Result<TimedEvent, CodecError> {
+  let mut reader = ByteReader { bytes: record, offset: 0 };
+  let timestamp = Timestamp(u64::from_le_bytes(reader.array()?));
+  let target_length = u16::from_le_bytes(reader.array()?) as usize;
+  let target = String::from_utf8(reader.take(target_length)?.to_vec())
+    .map_err(|error| CodecError::InvalidField { field: "target", value: error.to_string() })?;
+
+  let tag = reader.u8()?;
+  let event = match tag {
+    TAG_LOAD => WebEvent::WELoad(reader.u8()? != 0),
+    TAG_CLICK => {
+      let x = i64::from_le_bytes(reader.array()?);
+      let y = i64::from_le_bytes(reader.array()?);
+      let button_code = reader.u8()?;
+      let button = BUTTONS.get(button_code as usize).map(|(button, _)| *button)
+        .ok_or(CodecError::InvalidField { field: "button", value: button_code.to_string() })?;
+      WebEvent::WEClick(MouseClick { x, y, button, modifiers: modifiers_from_bits(reader.u8()?) })
+    },
+    TAG_KEYS => {
+      let modifiers = modifiers_from_bits(reader.u8()?);
+      let key = match reader.u8()? {
+        KEY_CHAR => {
+          let code = u32::from_le_bytes(reader.array()?);
+          Key::Char(char::from_u32(code).ok_or(CodecError::InvalidField { field: "key", value: code.to_string() })?)
+        },
+        KEY_NAMED => match reader.u8()? {
+          FUNCTION_KEY_CODE => {
+            let number = reader.u8()?;
+            Key::Named(NamedKey::function(number)
+              .ok_or(CodecError::InvalidField { field: "key", value: format!("F{}", number) })?)
+          },
+          code => Key::Named(*NAMED_KEYS.get(code as usize)
+            .ok_or(CodecError::InvalidField { field: "key", value: code.to_string() })?),
+        },
+        kind => return Err(CodecError::InvalidField { field: "key kind", value: kind.to_string() }),
+      };
+      WebEvent::WEKeys(KeyPress(modifiers, key))
+    },
+    _ => return Err(CodecError::InvalidField { field: "type", value: tag.to_string() }),
+  };
+  // Anything left in the record was added by a newer writer and is ignored
+  Ok(TimedEvent { timestamp, target: TargetId(target), event })
+}
+
+pub fn decode_binary(bytes: &[u8]) -> Result<Vec<TimedEvent>, CodecError> {
+  let mut reader = ByteReader { bytes, offset: 0 };
+  if reader.take(BINARY_MAGIC.len()).ok() != Some(&BINARY_MAGIC[..]) {
+    return Err(CodecError::BadMagic);
+  }
+  let version = reader.u8()? as u32;
+  let decode_record = match version {
+    1 => decode_record_v1,
+    _ => return Err(CodecError::UnsupportedVersion(version)),
+  };
+
+  let mut events = Vec::new();
+  while reader.offset < bytes.len() {
+    let length = u32::from_le_bytes(reader.array()?) as usize;
+    let record = reader.take(length)?;
+    let event = decode_record(record)
+      .map_err(|error| CodecError::AtRecord { record: events.len() + 1, error: Box::new(error) })?;
+    events.push(event);
+  }
+  Ok(events)
+}
+
+pub fn write_binary<W: Write>(writer: &mut W, events: &[TimedEvent]) -> Result<(), CodecError> {
+  writer.write_all(&encode_binary(events)?)?;
+  Ok(())
+}
+
+pub fn read_binary<R: Read>(mut reader: R) -> Result<Vec<TimedEvent>, CodecError> {
+  let mut bytes = Vec::new();
+  reader.read_to_end(&mut bytes)?;
+  decode_binary(&bytes)
+}
+
+// Read either format, telling them apart by the binary header
+pub fn read_any<R: Read>(mut reader: R) -> Result<Vec<TimedEvent>, CodecError> {
+  let mut bytes = Vec::new();
+  reader.read_to_end(&mut bytes)?;
+  if bytes.starts_with(BINARY_MAGIC) {
+    decode_binary(&bytes)
+  } else {
+    read_json_lines(&bytes[..])
+  }
+}
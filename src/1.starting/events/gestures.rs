@@ -0,0 +1,263 @@
+// ======================== GESTURES ========================
+// A MouseClick is one point in time. People do more than click: they double click, press
+// and hold, drag things around or draw a quick stroke with the right button held down.
+// The GestureRecognizer watches pointer samples and turns them into those bigger gestures:
+//
+//   > Click with a count: 1 for a single click, 2 for a double click, 3 for a triple click
+//   > LongPress when the button is held without moving for long enough
+//   > Drag with where it started and where it ended
+//   > Stroke (left, right, up, down or a circle) for drags made with the stroke button
+//
+// Every time and distance limit lives in GestureConfig so each app can tune them.
+
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use super::{MouseButton, MouseClick, TimedEvent, Timestamp, WebEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+  // Clicks closer together than this (in time and pixels) count as a double or triple click
+  pub multi_click_interval: Duration,
+  pub multi_click_distance: f64,
+  // Moving further than this with the button down turns a press into a drag
+  pub drag_distance: f64,
+  // Holding the button at least this long without dragging is a long press
+  pub long_press: Duration,
+  // Drags with this button are read as strokes, None turns strokes off
+  pub stroke_button: Option<MouseButton>,
+  // Shorter paths are too small to tell which shape they are
+  pub min_stroke_length: f64,
+}
+
+impl Default for GestureConfig {
+  fn default() -> GestureConfig {
+    GestureConfig {
+      multi_click_interval: Duration::from_millis(400),
+      multi_click_distance: 4.0,
+      drag_distance: 5.0,
+      long_press: Duration::from_millis(600),
+      stroke_button: Some(MouseButton::Right),
+      min_stroke_length: 30.0,
+    }
+  }
+}
+
+// What the pointer did. A WEClick from the event model becomes a Click, which is a press
+// and a release at the same place and time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pointer {
+  Press(MouseClick),
+  Release(MouseClick),
+  Click(MouseClick),
+  Move { x: i64, y: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerSample {
+  pub at: Timestamp,
+  pub pointer: Pointer,
+}
+
+impl PointerSample {
+  // Only WEClick events carry pointer data
+  pub fn from_event(event: &TimedEvent) -> Option<PointerSample> {
+    match event.event {
+      WebEvent::WEClick(click) => Some(PointerSample { at: event.timestamp, pointer: Pointer::Click(click) }),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrokeShape {
+  Left,
+  Right,
+  Up,
+  Down,
+  Circle { clockwise: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gesture {
+  Click { at: Timestamp, x: i64, y: i64, button: MouseButton, count: u8 },
+  LongPress { at: Timestamp, x: i64, y: i64, button: MouseButton, held: Duration },
+  Drag { button: MouseButton, start: (i64, i64), end: (i64, i64), started_at: Timestamp, ended_at: Timestamp },
+  Stroke { shape: StrokeShape, start: (i64, i64), end: (i64, i64), started_at: Timestamp, ended_at: Timestamp },
+}
+
+// The button that is currently down and everything that happened since it went down
+#[derive(Debug, Clone)]
+struct Press {
+  button: MouseButton,
+  at: Timestamp,
+  path: Vec<(i64, i64)>,
+  dragging: bool,
+  long_press_reported: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LastClick {
+  at: Timestamp,
+  x: i64,
+  y: i64,
+  button: MouseButton,
+  count: u8,
+}
+
+fn distance(a: (i64, i64), b: (i64, i64)) -> f64 {
+  ((a.0 - b.0) as f64).hypot((a.1 - b.1) as f64)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GestureRecognizer {
+  pub config: GestureConfig,
+  press: Option<Press>,
+  last_click: Option<LastClick>,
+}
+
+impl GestureRecognizer {
+  pub fn new(config: GestureConfig) -> GestureRecognizer {
+    GestureRecognizer { config, press: None, last_click: None }
+  }
+
+  // Run a whole recording through a fresh recognizer
+  pub fn analyse(config: GestureConfig, samples: &[PointerSample]) -> Vec<Gesture> {
+    let mut recognizer = GestureRecognizer::new(config);
+    samples.iter().flat_map(|sample| recognizer.feed(sample)).collect()
+  }
+
+  pub fn feed(&mut self, sample: &PointerSample) -> Vec<Gesture> {
+    let mut gestures = Vec::new();
+    // A long press may have become due before this sample arrived
+    gestures.extend(self.tick(sample.at));
+
+    match sample.pointer {
+      Pointer::Press(click) => {
+        self.press = Some(Press {
+          button: click.button,
+          at: sample.at,
+          path: vec![(click.x, click.y)],
+          dragging: false,
+          long_press_reported: false,
+        });
+      },
+      Pointer::Move { x, y } => {
+        let drag_distance = self.config.drag_distance;
+        if let Some(press) = self.press.as_mut() {
+          press.path.push((x, y));
+          if distance(press.path[0], (x, y)) > drag_distance {
+            press.dragging = true;
+          }
+        }
+      },
+      Pointer::Release(click) => {
+        if let Some(mut press) = self.press.take() {
+          press.path.push((click.x, click.y));
+          if distance(press.path[0], (click.x, click.y)) > self.config.drag_distance {
+            press.dragging = true;
+          }
+          gestures.extend(self.finish_press(press, sample.at, click));
+        }
+      },
+      Pointer::Click(click) => gestures.push(self.click(sample.at, click)),
+    }
+    gestures
+  }
+
+  // Call this while nothing else is happening to report a long press as soon as it is due
+  pub fn tick(&mut self, now: Timestamp) -> Option<Gesture> {
+    let long_press = self.config.long_press;
+    let press = self.press.as_mut()?;
+    let held = now.duration_since(press.at);
+    if press.dragging || press.long_press_reported || held < long_press {
+      return None;
+    }
+    press.long_press_reported = true;
+    let (x, y) = press.path[0];
+    Some(Gesture::LongPress { at: now, x, y, button: press.button, held })
+  }
+
+  fn finish_press(&mut self, press: Press, at: Timestamp, release: MouseClick) -> Option<Gesture> {
+    let start = press.path[0];
+    let end = (release.x, release.y);
+    if press.dragging {
+      self.last_click = None;
+      if Some(press.button) == self.config.stroke_button {
+        if let Some(shape) = classify_stroke(&press.path, self.config.min_stroke_length) {
+          return Some(Gesture::Stroke { shape, start, end, started_at: press.at, ended_at: at });
+        }
+      }
+      return Some(Gesture::Drag { button: press.button, start, end, started_at: press.at, ended_at: at });
+    }
+    if press.long_press_reported || at.duration_since(press.at) >= self.config.long_press {
+      self.last_click = None;
+      if press.long_press_reported {
+        return None;
+      }
+      let (x, y) = start;
+      return Some(Gesture::LongPress { at, x, y, button: press.button, held: at.duration_since(press.at) });
+    }
+    Some(self.click(at, MouseClick { x: start.0, y: start.1, ..release }))
+  }
+
+  // Count this click as part of a double or triple click when it follows the last one
+  // closely enough. After a triple click the count starts again at 1.
+  fn click(&mut self, at: Timestamp, click: MouseClick) -> Gesture {
+    let count = match self.last_click {
+      Some(last) if last.button == click.button
+        && last.count < 3
+        && at.duration_since(last.at) <= self.config.multi_click_interval
+        && distance((last.x, last.y), (click.x, click.y)) <= self.config.multi_click_distance => last.count + 1,
+      _ => 1,
+    };
+    self.last_click = Some(LastClick { at, x: click.x, y: click.y, button: click.button, count });
+    Gesture::Click { at, x: click.x, y: click.y, button: click.button, count }
+  }
+}
+
+// Decide what shape a drag path draws, or None if it isn't clearly one of them.
+// Screen coordinates grow downwards, so a negative dy is an upward stroke.
+fn classify_stroke(path: &[(i64, i64)], min_length: f64) -> Option<StrokeShape> {
+  let length: f64 = path.windows(2).map(|pair| distance(pair[0], pair[1])).sum();
+  if length < min_length {
+    return None;
+  }
+
+  // Add up how much the direction turns along the path: a full circle turns 2 PI
+  let mut turned = 0.0;
+  let mut previous_angle: Option<f64> = None;
+  for pair in path.windows(2) {
+    if pair[0] == pair[1] {
+      continue;
+    }
+    let angle = ((pair[1].1 - pair[0].1) as f64).atan2((pair[1].0 - pair[0].0) as f64);
+    if let Some(previous) = previous_angle {
+      let mut change = angle - previous;
+      while change > PI {
+        change -= 2.0 * PI;
+      }
+      while change <= -PI {
+        change += 2.0 * PI;
+      }
+      turned += change;
+    }
+    previous_angle = Some(angle);
+  }
+  let start = path[0];
+  let end = path[path.len() - 1];
+  if turned.abs() >= 1.75 * PI && distance(start, end) < length * 0.2 {
+    // With y pointing down, a positive turn is clockwise on screen
+    return Some(StrokeShape::Circle { clockwise: turned > 0.0 });
+  }
+
+  let dx = (end.0 - start.0) as f64;
+  let dy = (end.1 - start.1) as f64;
+  if dx.abs() >= 2.0 * dy.abs() {
+    Some(if dx < 0.0 { StrokeShape::Left } else { StrokeShape::Right })
+  } else if dy.abs() >= 2.0 * dx.abs() {
+    Some(if dy < 0.0 { StrokeShape::Up } else { StrokeShape::Down })
+  } else {
+    None
+  }
+}
@@ -0,0 +1,301 @@
+// ======================== TINY JSON ========================
+// Just enough JSON to write and read our event recordings without pulling in a crate.
+// Numbers keep their original text so big i64/u64 values never lose precision going
+// through an f64.
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+  Null,
+  Bool(bool),
+  Number(String),
+  String(String),
+  Array(Vec<JsonValue>),
+  // Objects keep the order their keys were written in
+  Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+  pub fn get(&self, key: &str) -> Option<&JsonValue> {
+    match self {
+      JsonValue::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      JsonValue::String(text) => Some(text),
+      _ => None,
+    }
+  }
+
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      JsonValue::Bool(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  pub fn as_i64(&self) -> Option<i64> {
+    match self {
+      JsonValue::Number(text) => text.parse().ok(),
+      _ => None,
+    }
+  }
+
+  pub fn as_u64(&self) -> Option<u64> {
+    match self {
+      JsonValue::Number(text) => text.parse().ok(),
+      _ => None,
+    }
+  }
+
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      JsonValue::Number(text) => text.parse().ok(),
+      _ => None,
+    }
+  }
+
+  pub fn as_array(&self) -> Option<&[JsonValue]> {
+    match self {
+      JsonValue::Array(items) => Some(items),
+      _ => None,
+    }
+  }
+
+  // Write compact JSON, without spaces or newlines, so each value fits on one line
+  pub fn to_json(&self) -> String {
+    let mut out = String::new();
+    self.write_to(&mut out);
+    out
+  }
+
+  fn write_to(&self, out: &mut String) {
+    match self {
+      JsonValue::Null => out.push_str("null"),
+      JsonValue::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+      JsonValue::Number(text) => out.push_str(text),
+      JsonValue::String(text) => write_string(text, out),
+      JsonValue::Array(items) => {
+        out.push('[');
+        for (index, item) in items.iter().enumerate() {
+          if index > 0 {
+            out.push(',');
+          }
+          item.write_to(out);
+        }
+        out.push(']');
+      },
+      JsonValue::Object(fields) => {
+        out.push('{');
+        for (index, (name, value)) in fields.iter().enumerate() {
+          if index > 0 {
+            out.push(',');
+          }
+          write_string(name, out);
+          out.push(':');
+          value.write_to(out);
+        }
+        out.push('}');
+      },
+    }
+  }
+}
+
+fn write_string(text: &str, out: &mut String) {
+  out.push('"');
+  for c in text.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if c.is_control() => {
+        let _ = write!(out, "\\u{:04x}", c as u32);
+      },
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+}
+
+// Arrays and objects inside each other deeper than this are refused. Every level is a
+// recursive call, and a line of "[[[[..." must not be able to overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+// Parse one complete JSON value; anything but whitespace after it is an error
+pub fn parse(text: &str) -> Result<JsonValue, String> {
+  let mut parser = Parser { chars: text.char_indices().peekable(), text, depth: 0 };
+  let value = parser.value()?;
+  parser.skip_whitespace();
+  match parser.chars.peek() {
+    None => Ok(value),
+    Some((offset, _)) => Err(format!("unexpected text after the value at byte {}", offset)),
+  }
+}
+
+struct Parser<'a> {
+  chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+  text: &'a str,
+  // How many arrays and objects we are inside
+  depth: usize,
+}
+
+impl Parser<'_> {
+  fn skip_whitespace(&mut self) {
+    while matches!(self.chars.peek(), Some((_, ' ' | '\t' | '\n' | '\r'))) {
+      self.chars.next();
+    }
+  }
+
+  fn expect(&mut self, expected: char) -> Result<(), String> {
+    match self.chars.next() {
+      Some((_, c)) if c == expected => Ok(()),
+      Some((offset, c)) => Err(format!("expected '{}' but found '{}' at byte {}", expected, c, offset)),
+      None => Err(format!("expected '{}' but the text ended", expected)),
+    }
+  }
+
+  fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, String> {
+    for expected in word.chars() {
+      self.expect(expected)?;
+    }
+    Ok(value)
+  }
+
+  fn value(&mut self) -> Result<JsonValue, String> {
+    self.skip_whitespace();
+    match self.chars.peek().copied() {
+      Some((offset, c @ ('{' | '['))) => {
+        if self.depth == MAX_DEPTH {
+          return Err(format!("more than {} nested arrays and objects at byte {}", MAX_DEPTH, offset));
+        }
+        self.depth += 1;
+        let value = if c == '{' { self.object() } else { self.array() };
+        self.depth -= 1;
+        value
+      },
+      Some((_, '"')) => self.string().map(JsonValue::String),
+      Some((_, 't')) => self.literal("true", JsonValue::Bool(true)),
+      Some((_, 'f')) => self.literal("false", JsonValue::Bool(false)),
+      Some((_, 'n')) => self.literal("null", JsonValue::Null),
+      Some((start, c)) if c == '-' || c.is_ascii_digit() => {
+        let mut end = start;
+        while let Some((offset, c)) = self.chars.peek().copied() {
+          if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            break;
+          }
+          end = offset + 1;
+          self.chars.next();
+        }
+        let number = &self.text[start..end];
+        match number.parse::<f64>() {
+          Ok(_) => Ok(JsonValue::Number(number.to_string())),
+          Err(_) => Err(format!("'{}' is not a number", number)),
+        }
+      },
+      Some((offset, c)) => Err(format!("unexpected '{}' at byte {}", c, offset)),
+      None => Err(String::from("expected a value but the text ended")),
+    }
+  }
+
+  fn array(&mut self) -> Result<JsonValue, String> {
+    self.expect('[')?;
+    let mut items = Vec::new();
+    self.skip_whitespace();
+    if let Some((_, ']')) = self.chars.peek() {
+      self.chars.next();
+      return Ok(JsonValue::Array(items));
+    }
+    loop {
+      items.push(self.value()?);
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some((_, ',')) => continue,
+        Some((_, ']')) => return Ok(JsonValue::Array(items)),
+        Some((offset, c)) => return Err(format!("expected ',' or ']' but found '{}' at byte {}", c, offset)),
+        None => return Err(String::from("the array is not closed")),
+      }
+    }
+  }
+
+  fn object(&mut self) -> Result<JsonValue, String> {
+    self.expect('{')?;
+    let mut fields = Vec::new();
+    self.skip_whitespace();
+    if let Some((_, '}')) = self.chars.peek() {
+      self.chars.next();
+      return Ok(JsonValue::Object(fields));
+    }
+    loop {
+      self.skip_whitespace();
+      let name = self.string()?;
+      self.skip_whitespace();
+      self.expect(':')?;
+      fields.push((name, self.value()?));
+      self.skip_whitespace();
+      match self.chars.next() {
+        Some((_, ',')) => continue,
+        Some((_, '}')) => return Ok(JsonValue::Object(fields)),
+        Some((offset, c)) => return Err(format!("expected ',' or '}}' but found '{}' at byte {}", c, offset)),
+        None => return Err(String::from("the object is not closed")),
+      }
+    }
+  }
+
+  fn hex_escape(&mut self) -> Result<u32, String> {
+    let mut code = 0;
+    for _ in 0..4 {
+      match self.chars.next().and_then(|(_, c)| c.to_digit(16)) {
+        Some(digit) => code = code * 16 + digit,
+        None => return Err(String::from("\\u must be followed by four hex digits")),
+      }
+    }
+    Ok(code)
+  }
+
+  fn string(&mut self) -> Result<String, String> {
+    self.expect('"')?;
+    let mut text = String::new();
+    loop {
+      match self.chars.next() {
+        Some((_, '"')) => return Ok(text),
+        Some((_, '\\')) => match self.chars.next() {
+          Some((_, '"')) => text.push('"'),
+          Some((_, '\\')) => text.push('\\'),
+          Some((_, '/')) => text.push('/'),
+          Some((_, 'b')) => text.push('\u{8}'),
+          Some((_, 'f')) => text.push('\u{c}'),
+          Some((_, 'n')) => text.push('\n'),
+          Some((_, 'r')) => text.push('\r'),
+          Some((_, 't')) => text.push('\t'),
+          Some((_, 'u')) => {
+            let mut code = self.hex_escape()?;
+            // Characters outside the BMP are written as a UTF-16 surrogate pair, high half first
+            if (0xDC00..0xE000).contains(&code) {
+              return Err(format!("\\u{:04x} is the low half of a surrogate pair without the high half", code));
+            }
+            if (0xD800..0xDC00).contains(&code) {
+              let missing = || format!("\\u{:04x} must be followed by the low half of a surrogate pair", code);
+              self.expect('\\').map_err(|_| missing())?;
+              self.expect('u').map_err(|_| missing())?;
+              let low = self.hex_escape()?;
+              if !(0xDC00..0xE000).contains(&low) {
+                return Err(missing());
+              }
+              code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+            }
+            text.push(char::from_u32(code).ok_or(format!("\\u{:x} is not a valid character", code))?);
+          },
+          _ => return Err(String::from("unknown escape sequence in string")),
+        },
+        Some((_, c)) => text.push(c),
+        None => return Err(String::from("the string is not closed")),
+      }
+    }
+  }
+}
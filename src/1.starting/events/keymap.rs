@@ -0,0 +1,316 @@
+// ======================== KEYMAP ========================
+// Resolve WEKeys events to named commands. Bindings are loaded from a text file:
+//
+//   # Lines starting with # are comments
+//   Ctrl+N => new_file
+//   Ctrl+K Ctrl+C => comment_line      <- a chord: Ctrl+K, then Ctrl+C
+//
+//   [editor]                           <- a layer that only applies in the "editor" context
+//   Ctrl+N => new_cursor               <- overrides the base binding while editing
+//
+// Bindings before the first [section] belong to the base layer. When several contexts are
+// active, the first one given wins, and the base layer is always checked last.
+//
+// Loading never silently drops a binding. Problems that make the file unusable are errors;
+// bindings that can never fire or that hide other bindings are returned as warnings.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::keys::{KeyChord, KeyParseError};
+use super::{KeyPress, Timestamp};
+
+pub const BASE_LAYER: &str = "base";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+  pub chord: KeyChord,
+  pub command: String,
+  pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+  pub name: String,
+  pub bindings: Vec<Binding>,
+}
+
+// The chord is exactly these presses, letters compared regardless of case
+fn is_chord(chord: &KeyChord, sequence: &[KeyPress]) -> bool {
+  chord.steps().len() == sequence.len() && chord.starts_with(sequence)
+}
+
+impl Layer {
+  fn exact(&self, sequence: &[KeyPress]) -> Option<&Binding> {
+    // The last binding of a duplicated chord wins, like a later assignment would
+    self.bindings.iter().rev().find(|binding| is_chord(&binding.chord, sequence))
+  }
+
+  fn has_longer(&self, sequence: &[KeyPress]) -> bool {
+    self.bindings.iter().any(|binding| binding.chord.steps().len() > sequence.len() && binding.chord.starts_with(sequence))
+  }
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+  Io(std::io::Error),
+  MissingArrow { line: usize },
+  EmptyCommand { line: usize },
+  InvalidChord { line: usize, error: KeyParseError },
+  InvalidSection { line: usize, text: String },
+}
+
+impl fmt::Display for KeymapError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      KeymapError::Io(error) => write!(f, "cannot read keymap: {}", error),
+      KeymapError::MissingArrow { line } => write!(f, "line {}: expected '<keys> => <command>'", line),
+      KeymapError::EmptyCommand { line } => write!(f, "line {}: the command after '=>' is empty", line),
+      KeymapError::InvalidChord { line, error } => write!(f, "line {}: {}", line, error),
+      KeymapError::InvalidSection { line, text } => write!(f, "line {}: '{}' is not a valid [layer] header", line, text),
+    }
+  }
+}
+
+impl std::error::Error for KeymapError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      KeymapError::Io(error) => Some(error),
+      KeymapError::InvalidChord { error, .. } => Some(error),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapWarning {
+  // The same chord is bound twice in one layer, only the later line is used
+  Duplicate { layer: String, chord: KeyChord, first_line: usize, second_line: usize },
+  // `prefix` fires as soon as it is typed, so `longer` can never be reached. The two can be in
+  // different layers, then it only happens while both layers are active.
+  Shadowed { layer: String, longer: KeyChord, line: usize, prefix_layer: String, prefix: KeyChord },
+  // A context layer replaces a base binding while that context is active
+  Overrides { layer: String, chord: KeyChord, command: String, base_command: String, line: usize },
+}
+
+impl fmt::Display for KeymapWarning {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      KeymapWarning::Duplicate { layer, chord, first_line, second_line } => {
+        write!(f, "[{}] {} is bound on line {} and again on line {}, line {} wins",
+          layer, chord, first_line, second_line, second_line)
+      },
+      KeymapWarning::Shadowed { layer, longer, line, prefix_layer, prefix } if layer == prefix_layer => {
+        write!(f, "[{}] {} on line {} can never fire because {} fires first", layer, longer, line, prefix)
+      },
+      KeymapWarning::Shadowed { layer, longer, line, prefix_layer, prefix } if prefix_layer == BASE_LAYER => {
+        write!(f, "[{}] {} on line {} can never fire because the base binding {} fires first",
+          layer, longer, line, prefix)
+      },
+      KeymapWarning::Shadowed { layer, longer, line, prefix_layer, prefix } => {
+        write!(f, "[{}] {} on line {} can't fire while [{}] is active because its {} fires first",
+          layer, longer, line, prefix_layer, prefix)
+      },
+      KeymapWarning::Overrides { layer, chord, command, base_command, line } => {
+        write!(f, "[{}] {} => {} on line {} overrides the base binding {}", layer, chord, command, line, base_command)
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+  // The base layer is always first
+  pub layers: Vec<Layer>,
+}
+
+impl Keymap {
+  pub fn parse(text: &str) -> Result<(Keymap, Vec<KeymapWarning>), KeymapError> {
+    let mut layers = vec![Layer { name: String::from(BASE_LAYER), bindings: Vec::new() }];
+    // Bindings go to the most recent [section], which is the base layer until the first one
+    let mut current = 0;
+    for (index, raw_line) in text.lines().enumerate() {
+      let line = index + 1;
+      let content = raw_line.trim();
+      if content.is_empty() || content.starts_with('#') {
+        continue;
+      }
+
+      if let Some(header) = content.strip_prefix('[') {
+        let name = match header.strip_suffix(']').map(str::trim) {
+          Some(name) if !name.is_empty() && !name.contains(char::is_whitespace) => name,
+          _ => return Err(KeymapError::InvalidSection { line, text: content.to_string() }),
+        };
+        current = match layers.iter().position(|layer| layer.name == name) {
+          Some(position) => position,
+          None => {
+            layers.push(Layer { name: name.to_string(), bindings: Vec::new() });
+            layers.len() - 1
+          },
+        };
+        continue;
+      }
+
+      let (keys, command) = content.split_once("=>").ok_or(KeymapError::MissingArrow { line })?;
+      let command = command.trim();
+      if command.is_empty() {
+        return Err(KeymapError::EmptyCommand { line });
+      }
+      let chord = keys.trim().parse::<KeyChord>().map_err(|error| KeymapError::InvalidChord { line, error })?;
+      layers[current].bindings.push(Binding { chord, command: command.to_string(), line });
+    }
+
+    let keymap = Keymap { layers };
+    let warnings = keymap.check();
+    Ok((keymap, warnings))
+  }
+
+  pub fn load(path: &Path) -> Result<(Keymap, Vec<KeymapWarning>), KeymapError> {
+    let text = fs::read_to_string(path).map_err(KeymapError::Io)?;
+    Keymap::parse(&text)
+  }
+
+  pub fn layer(&self, name: &str) -> Option<&Layer> {
+    self.layers.iter().find(|layer| layer.name == name)
+  }
+
+  // Look for duplicates, shadowed chords and context overrides
+  pub fn check(&self) -> Vec<KeymapWarning> {
+    let mut warnings = Vec::new();
+    for layer in &self.layers {
+      for (index, binding) in layer.bindings.iter().enumerate() {
+        let earlier = &layer.bindings[..index];
+        if let Some(first) = earlier.iter().find(|other| is_chord(&other.chord, binding.chord.steps())) {
+          warnings.push(KeymapWarning::Duplicate {
+            layer: layer.name.clone(),
+            chord: binding.chord.clone(),
+            first_line: first.line,
+            second_line: binding.line,
+          });
+        }
+      }
+    }
+
+    // Any set of contexts can be active together, and an exact match in any active layer wins
+    // over waiting for a longer chord, so a short chord in one layer hides longer ones starting
+    // with it in every layer, its own included
+    for layer in &self.layers {
+      for binding in &layer.bindings {
+        for other_layer in &self.layers {
+          for other in &other_layer.bindings {
+            let longer = other.chord.steps().len() > binding.chord.steps().len();
+            if longer && other.chord.starts_with(binding.chord.steps()) {
+              warnings.push(KeymapWarning::Shadowed {
+                layer: other_layer.name.clone(),
+                longer: other.chord.clone(),
+                line: other.line,
+                prefix_layer: layer.name.clone(),
+                prefix: binding.chord.clone(),
+              });
+            }
+          }
+        }
+      }
+    }
+
+    let base = &self.layers[0];
+    for layer in &self.layers[1..] {
+      for binding in &layer.bindings {
+        if let Some(base_binding) = base.exact(binding.chord.steps()) {
+          warnings.push(KeymapWarning::Overrides {
+            layer: layer.name.clone(),
+            chord: binding.chord.clone(),
+            command: binding.command.clone(),
+            base_command: base_binding.command.clone(),
+            line: binding.line,
+          });
+        }
+      }
+    }
+    warnings
+  }
+
+  // The layers to search, most specific first, ending with the base layer
+  fn active_layers<'a>(&'a self, contexts: &[&str]) -> Vec<&'a Layer> {
+    let mut active: Vec<&Layer> = contexts.iter().filter_map(|context| self.layer(context)).collect();
+    active.push(&self.layers[0]);
+    active
+  }
+
+  pub fn resolve(&self, contexts: &[&str], sequence: &[KeyPress]) -> Resolution {
+    let active = self.active_layers(contexts);
+    for layer in &active {
+      if let Some(binding) = layer.exact(sequence) {
+        return Resolution::Command(binding.command.clone());
+      }
+    }
+    if active.iter().any(|layer| layer.has_longer(sequence)) {
+      Resolution::Pending
+    } else {
+      Resolution::NoMatch
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+  Command(String),
+  // The keys so far start a chord, wait for the next one
+  Pending,
+  NoMatch,
+}
+
+// Feeds key presses one at a time and remembers the unfinished part of a chord.
+// If the next key takes longer than `timeout` to arrive, the chord starts over.
+#[derive(Debug, Clone)]
+pub struct ChordMatcher {
+  pub timeout: Duration,
+  pending: Vec<KeyPress>,
+  last_press: Option<Timestamp>,
+}
+
+impl Default for ChordMatcher {
+  fn default() -> ChordMatcher {
+    ChordMatcher::new(Duration::from_millis(1000))
+  }
+}
+
+impl ChordMatcher {
+  pub fn new(timeout: Duration) -> ChordMatcher {
+    ChordMatcher { timeout, pending: Vec::new(), last_press: None }
+  }
+
+  pub fn pending(&self) -> &[KeyPress] {
+    &self.pending
+  }
+
+  pub fn reset(&mut self) {
+    self.pending.clear();
+    self.last_press = None;
+  }
+
+  pub fn feed(&mut self, keymap: &Keymap, contexts: &[&str], press: KeyPress, at: Timestamp) -> Resolution {
+    if let Some(last) = self.last_press {
+      if at.duration_since(last) > self.timeout {
+        self.pending.clear();
+      }
+    }
+    self.last_press = Some(at);
+    // Recorded presses keep the char that was typed, bindings are parsed as "Ctrl+N"
+    let press = press.normalized();
+    self.pending.push(press);
+
+    let mut resolution = keymap.resolve(contexts, &self.pending);
+    // A broken chord like "Ctrl+K Ctrl+N" still lets Ctrl+N do its own thing
+    if resolution == Resolution::NoMatch && self.pending.len() > 1 {
+      self.pending = vec![press];
+      resolution = keymap.resolve(contexts, &self.pending);
+    }
+    if resolution != Resolution::Pending {
+      self.pending.clear();
+    }
+    resolution
+  }
+}
@@ -0,0 +1,298 @@
+// ======================== KEY CHORDS ========================
+// Turn text like "Ctrl+Shift+N", "Alt+F4" or "Cmd+ArrowLeft" into a KeyPress and back.
+// A KeyChord is several key presses typed one after the other, like "Ctrl+K Ctrl+C".
+//
+// Modifiers are always printed in the same order (Ctrl, Alt, Shift, Meta), so
+// "Shift+Ctrl+n" and "Ctrl+Shift+N" parse to the same KeyPress and print the same way.
+// Letters are stored in uppercase because a shortcut names a key, not the text it types.
+// What a KeyPress prints parses back to the same press, once its key is normalized: parsing
+// only gives uppercase letters, NamedKey::Space rather than Char(' '), and F1 to F24.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Key, KeyPress, Modifiers, NamedKey};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyParseErrorKind {
+  Empty,
+  MissingKey,
+  UnknownModifier(String),
+  UnknownKey(String),
+  DuplicateModifier(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyParseError {
+  pub input: String,
+  pub kind: KeyParseErrorKind,
+}
+
+impl fmt::Display for KeyParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "cannot parse key '{}': ", self.input)?;
+    match &self.kind {
+      KeyParseErrorKind::Empty => write!(f, "nothing to parse"),
+      KeyParseErrorKind::MissingKey => write!(f, "modifiers must be followed by a key, like Ctrl+N"),
+      KeyParseErrorKind::UnknownModifier(name) => {
+        write!(f, "unknown modifier '{}', expected Ctrl, Alt, Shift or Meta (Cmd, Win)", name)
+      },
+      KeyParseErrorKind::UnknownKey(name) => {
+        write!(f, "unknown key '{}', expected a single character or a name like Enter, F4 or ArrowLeft", name)
+      },
+      KeyParseErrorKind::DuplicateModifier(name) => write!(f, "modifier '{}' is repeated", name),
+    }
+  }
+}
+
+impl std::error::Error for KeyParseError {}
+
+// The modifier with this name, accepting the common spellings on every platform
+fn modifier_named(name: &str) -> Option<Modifiers> {
+  match name.to_ascii_lowercase().as_str() {
+    "ctrl" | "control" | "ctl" => Some(Modifiers::CTRL),
+    "alt" | "option" | "opt" => Some(Modifiers::ALT),
+    "shift" => Some(Modifiers::SHIFT),
+    "meta" | "cmd" | "command" | "super" | "win" | "windows" => Some(Modifiers::META),
+    _ => None,
+  }
+}
+
+fn add_modifier(modifiers: &mut Modifiers, name: &str) -> Result<(), KeyParseErrorKind> {
+  let modifier = match modifier_named(name) {
+    Some(modifier) => modifier,
+    None => return Err(KeyParseErrorKind::UnknownModifier(name.to_string())),
+  };
+  if modifiers.contains(modifier) {
+    return Err(KeyParseErrorKind::DuplicateModifier(name.to_string()));
+  }
+  *modifiers = modifiers.with(modifier);
+  Ok(())
+}
+
+impl NamedKey {
+  // F1 to F24, the function keys a keyboard can have. Build F keys with this, not NamedKey::F.
+  pub fn function(number: u8) -> Option<NamedKey> {
+    match number {
+      1..=24 => Some(NamedKey::F(number)),
+      _ => None,
+    }
+  }
+}
+
+impl FromStr for NamedKey {
+  type Err = KeyParseErrorKind;
+
+  fn from_str(name: &str) -> Result<NamedKey, KeyParseErrorKind> {
+    let lower = name.to_ascii_lowercase();
+    let key = match lower.as_str() {
+      "enter" | "return" => NamedKey::Enter,
+      "escape" | "esc" => NamedKey::Escape,
+      "tab" => NamedKey::Tab,
+      "space" | "spacebar" => NamedKey::Space,
+      "backspace" => NamedKey::Backspace,
+      "delete" | "del" => NamedKey::Delete,
+      "insert" | "ins" => NamedKey::Insert,
+      "home" => NamedKey::Home,
+      "end" => NamedKey::End,
+      "pageup" | "pgup" => NamedKey::PageUp,
+      "pagedown" | "pgdn" => NamedKey::PageDown,
+      "arrowup" | "up" => NamedKey::ArrowUp,
+      "arrowdown" | "down" => NamedKey::ArrowDown,
+      "arrowleft" | "left" => NamedKey::ArrowLeft,
+      "arrowright" | "right" => NamedKey::ArrowRight,
+      _ => match lower.strip_prefix('f').map(|number| number.parse::<u8>()) {
+        Some(Ok(number)) => match NamedKey::function(number) {
+          Some(key) => key,
+          None => return Err(KeyParseErrorKind::UnknownKey(name.to_string())),
+        },
+        _ => return Err(KeyParseErrorKind::UnknownKey(name.to_string())),
+      },
+    };
+    Ok(key)
+  }
+}
+
+// The uppercase letter, unless it takes several chars: "ß" would become "SS", which is two keys
+fn uppercase(c: char) -> char {
+  let mut upper = c.to_uppercase();
+  match (upper.next(), upper.next()) {
+    (Some(upper), None) => upper,
+    _ => c,
+  }
+}
+
+impl Key {
+  // The key as parsing would give it. Recorded events keep the char that was typed, so 'n' and
+  // 'N' are the same key, and ' ' is Space, only once both are normalized.
+  pub fn normalized(self) -> Key {
+    match self {
+      Key::Char(' ') => Key::Named(NamedKey::Space),
+      Key::Char(c) => Key::Char(uppercase(c)),
+      named => named,
+    }
+  }
+}
+
+impl KeyPress {
+  // The same press with its key normalized, so a recorded Ctrl+'n' matches a parsed Ctrl+N
+  pub fn normalized(self) -> KeyPress {
+    KeyPress(self.0, self.1.normalized())
+  }
+}
+
+impl FromStr for Key {
+  type Err = KeyParseErrorKind;
+
+  fn from_str(name: &str) -> Result<Key, KeyParseErrorKind> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+      (None, _) => Err(KeyParseErrorKind::MissingKey),
+      (Some(c), None) if !c.is_control() => Ok(Key::Char(c).normalized()),
+      _ => name.parse::<NamedKey>().map(Key::Named),
+    }
+  }
+}
+
+// Just the modifiers, like "Ctrl+Shift". "none" is no modifiers at all.
+impl FromStr for Modifiers {
+  type Err = KeyParseError;
+
+  fn from_str(input: &str) -> Result<Modifiers, KeyParseError> {
+    let error = |kind| KeyParseError { input: input.to_string(), kind };
+    let text = input.trim();
+    if text.is_empty() {
+      return Err(error(KeyParseErrorKind::Empty));
+    }
+    let mut modifiers = Modifiers::NONE;
+    if text.eq_ignore_ascii_case("none") {
+      return Ok(modifiers);
+    }
+    for name in text.split('+') {
+      add_modifier(&mut modifiers, name.trim()).map_err(error)?;
+    }
+    Ok(modifiers)
+  }
+}
+
+impl FromStr for KeyPress {
+  type Err = KeyParseError;
+
+  fn from_str(input: &str) -> Result<KeyPress, KeyParseError> {
+    let error = |kind| KeyParseError { input: input.to_string(), kind };
+    let text = input.trim();
+    if text.is_empty() {
+      return Err(error(KeyParseErrorKind::Empty));
+    }
+
+    // "Ctrl++" and "+" use the plus key itself, so it can't be split like the rest
+    let (modifier_text, key_text) = if text == "+" {
+      ("", "+")
+    } else if let Some(rest) = text.strip_suffix("++") {
+      (rest, "+")
+    } else {
+      match text.rsplit_once('+') {
+        Some((modifier_text, key_text)) => (modifier_text, key_text),
+        None => ("", text),
+      }
+    };
+
+    let mut modifiers = Modifiers::NONE;
+    if !modifier_text.is_empty() {
+      for name in modifier_text.split('+') {
+        add_modifier(&mut modifiers, name.trim()).map_err(error)?;
+      }
+    }
+
+    // A trailing modifier ("Ctrl+Shift") is a missing key, not an unknown one
+    let key_text = key_text.trim();
+    if modifier_named(key_text).is_some() {
+      return Err(error(KeyParseErrorKind::MissingKey));
+    }
+    let key = key_text.parse::<Key>().map_err(error)?;
+    Ok(KeyPress(modifiers, key))
+  }
+}
+
+impl fmt::Display for NamedKey {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      NamedKey::F(number) => write!(f, "F{}", number),
+      _ => write!(f, "{:?}", self),
+    }
+  }
+}
+
+impl fmt::Display for Key {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Key::Char(' ') => write!(f, "Space"),
+      Key::Char(c) => write!(f, "{}", uppercase(*c)),
+      Key::Named(named) => write!(f, "{}", named),
+    }
+  }
+}
+
+// Prints "Ctrl+Alt+Shift+Meta" for the held modifiers, in that order, and "none" for none
+impl fmt::Display for Modifiers {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let names = [(self.ctrl, "Ctrl"), (self.alt, "Alt"), (self.shift, "Shift"), (self.meta, "Meta")];
+    let held: Vec<&str> = names.iter().filter(|(held, _)| *held).map(|(_, name)| *name).collect();
+    if held.is_empty() {
+      write!(f, "none")
+    } else {
+      write!(f, "{}", held.join("+"))
+    }
+  }
+}
+
+impl fmt::Display for KeyPress {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.0.is_empty() {
+      write!(f, "{}", self.1)
+    } else {
+      write!(f, "{}+{}", self.0, self.1)
+    }
+  }
+}
+
+// Key presses typed in sequence, like "Ctrl+K Ctrl+C"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord(pub Vec<KeyPress>);
+
+impl KeyChord {
+  pub fn steps(&self) -> &[KeyPress] {
+    &self.0
+  }
+
+  // True when `presses` are the first steps of this chord (or the whole chord), whatever the
+  // case of their letters
+  pub fn starts_with(&self, presses: &[KeyPress]) -> bool {
+    self.0.len() >= presses.len()
+      && self.0.iter().zip(presses).all(|(step, press)| step.normalized() == press.normalized())
+  }
+}
+
+impl FromStr for KeyChord {
+  type Err = KeyParseError;
+
+  fn from_str(input: &str) -> Result<KeyChord, KeyParseError> {
+    let steps = input.split_whitespace().map(|step| step.parse::<KeyPress>()).collect::<Result<Vec<_>, _>>()?;
+    if steps.is_empty() {
+      return Err(KeyParseError { input: input.to_string(), kind: KeyParseErrorKind::Empty });
+    }
+    Ok(KeyChord(steps))
+  }
+}
+
+impl fmt::Display for KeyChord {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for (index, step) in self.0.iter().enumerate() {
+      if index > 0 {
+        write!(f, " ")?;
+      }
+      write!(f, "{}", step)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,211 @@
+// ======================== LAYOUT AND HIT TESTING ========================
+// A click at (100, 250) only means something once we know what is drawn there. This module
+// keeps a tree of named rectangles (a page, a sidebar, a button inside it...) and answers
+// "which region did this click land on?", plus the list of its ancestors so the click can
+// bubble up from the button to the page like it does in a browser.
+//
+// The same point can be written in three coordinate spaces:
+//   > viewport: from the top-left corner of the visible window, what MouseClick stores
+//   > page: from the top-left corner of the whole page, viewport + how far the page scrolled
+//   > local: from the top-left corner of one region, after every ancestor's scroll
+//
+// Each space has its own point type so they can't be mixed up by accident.
+
+use super::bus::Propagation;
+use super::MouseClick;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ViewportPoint { pub x: i64, pub y: i64 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PagePoint { pub x: i64, pub y: i64 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LocalPoint { pub x: i64, pub y: i64 }
+
+impl MouseClick {
+  // MouseClick coordinates are measured in the viewport
+  pub fn viewport_point(&self) -> ViewportPoint {
+    ViewportPoint { x: self.x, y: self.y }
+  }
+
+  pub fn page_point(&self, viewport: &Viewport) -> PagePoint {
+    viewport.to_page(self.viewport_point())
+  }
+}
+
+// The visible window onto the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Viewport {
+  pub scroll_x: i64,
+  pub scroll_y: i64,
+  pub width: i64,
+  pub height: i64,
+}
+
+impl Viewport {
+  pub fn to_page(self, point: ViewportPoint) -> PagePoint {
+    PagePoint { x: point.x + self.scroll_x, y: point.y + self.scroll_y }
+  }
+
+  pub fn to_viewport(self, point: PagePoint) -> ViewportPoint {
+    ViewportPoint { x: point.x - self.scroll_x, y: point.y - self.scroll_y }
+  }
+
+  pub fn is_visible(&self, point: PagePoint) -> bool {
+    let point = self.to_viewport(point);
+    point.x >= 0 && point.y >= 0 && point.x < self.width && point.y < self.height
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+  pub x: i64,
+  pub y: i64,
+  pub width: i64,
+  pub height: i64,
+}
+
+impl Rect {
+  pub fn new(x: i64, y: i64, width: i64, height: i64) -> Rect {
+    Rect { x, y, width, height }
+  }
+
+  // The right and bottom edges belong to the next rectangle over
+  pub fn contains(&self, x: i64, y: i64) -> bool {
+    x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+  }
+}
+
+// A named rectangle. `rect` is relative to the parent's top-left corner, and `scroll` is how
+// far this region's own content is scrolled, which moves all of its children.
+// Regions with a higher `z` are drawn on top of their siblings; with equal z the later
+// sibling is on top. Children are clipped to their parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+  pub name: String,
+  pub rect: Rect,
+  pub z: i32,
+  pub scroll_x: i64,
+  pub scroll_y: i64,
+  pub children: Vec<Region>,
+}
+
+impl Region {
+  pub fn new(name: &str, rect: Rect) -> Region {
+    Region { name: String::from(name), rect, z: 0, scroll_x: 0, scroll_y: 0, children: Vec::new() }
+  }
+
+  pub fn with_z(mut self, z: i32) -> Region {
+    self.z = z;
+    self
+  }
+
+  pub fn with_scroll(mut self, scroll_x: i64, scroll_y: i64) -> Region {
+    self.scroll_x = scroll_x;
+    self.scroll_y = scroll_y;
+    self
+  }
+
+  pub fn with_child(mut self, child: Region) -> Region {
+    self.children.push(child);
+    self
+  }
+}
+
+// The result of a hit test: the region that was hit, then its parent, up to the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit<'a> {
+  pub path: Vec<&'a Region>,
+  pub local: LocalPoint,
+}
+
+impl<'a> Hit<'a> {
+  pub fn target(&self) -> &'a Region {
+    self.path[0]
+  }
+
+  pub fn names(&self) -> Vec<&'a str> {
+    self.path.iter().map(|region| region.name.as_str()).collect()
+  }
+
+  // Call `handler` for the target and then each ancestor, until one returns Stop.
+  // Returns the name of the region that stopped the click, if any.
+  pub fn bubble<F>(&self, mut handler: F) -> Option<&'a str>
+  where
+    F: FnMut(&Region) -> Propagation,
+  {
+    self.path.iter().find(|region| handler(region) == Propagation::Stop).map(|region| region.name.as_str())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+  // The root's rect is in page coordinates
+  pub root: Region,
+}
+
+impl Layout {
+  pub fn new(root: Region) -> Layout {
+    Layout { root }
+  }
+
+  // The topmost region under a page point
+  pub fn hit_test(&self, point: PagePoint) -> Option<Hit<'_>> {
+    let mut path = Vec::new();
+    // The path is already target first, as Hit wants it
+    let (x, y) = hit_region(&self.root, (0, 0), point, &mut path)?;
+    Some(Hit { local: LocalPoint { x: point.x - x, y: point.y - y }, path })
+  }
+
+  pub fn hit_test_click(&self, click: &MouseClick, viewport: &Viewport) -> Option<Hit<'_>> {
+    self.hit_test(click.page_point(viewport))
+  }
+
+  // Where the top-left corner of the named region is on the page
+  pub fn page_origin(&self, name: &str) -> Option<PagePoint> {
+    find_origin(&self.root, (0, 0), name).map(|(x, y)| PagePoint { x, y })
+  }
+
+  pub fn to_local(&self, name: &str, point: PagePoint) -> Option<LocalPoint> {
+    let origin = self.page_origin(name)?;
+    Some(LocalPoint { x: point.x - origin.x, y: point.y - origin.y })
+  }
+
+  pub fn to_page(&self, name: &str, point: LocalPoint) -> Option<PagePoint> {
+    let origin = self.page_origin(name)?;
+    Some(PagePoint { x: point.x + origin.x, y: point.y + origin.y })
+  }
+}
+
+// `offset` is where the parent's content starts on the page, already moved by its scroll.
+// Pushes the hit regions deepest first onto `path` and returns where the deepest one starts on
+// the page, worked out on the way down because names don't have to be unique.
+fn hit_region<'a>(region: &'a Region, offset: (i64, i64), point: PagePoint, path: &mut Vec<&'a Region>) -> Option<(i64, i64)> {
+  let page_rect = Rect::new(offset.0 + region.rect.x, offset.1 + region.rect.y, region.rect.width, region.rect.height);
+  if !page_rect.contains(point.x, point.y) {
+    return None;
+  }
+  let content = (page_rect.x - region.scroll_x, page_rect.y - region.scroll_y);
+
+  // Try the children from the top of the stack down
+  let mut order: Vec<(usize, &Region)> = region.children.iter().enumerate().collect();
+  order.sort_by(|(a_index, a), (b_index, b)| b.z.cmp(&a.z).then(b_index.cmp(a_index)));
+  for (_, child) in order {
+    if let Some(origin) = hit_region(child, content, point, path) {
+      path.push(region);
+      return Some(origin);
+    }
+  }
+  path.push(region);
+  Some((page_rect.x, page_rect.y))
+}
+
+fn find_origin(region: &Region, offset: (i64, i64), name: &str) -> Option<(i64, i64)> {
+  let origin = (offset.0 + region.rect.x, offset.1 + region.rect.y);
+  if region.name == name {
+    return Some(origin);
+  }
+  let content = (origin.0 - region.scroll_x, origin.1 - region.scroll_y);
+  region.children.iter().find_map(|child| find_origin(child, content, name))
+}
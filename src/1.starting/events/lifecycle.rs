@@ -0,0 +1,267 @@
+// ======================== PAGE LIFECYCLE ========================
+// WELoad(bool) only says "loaded" or "not loaded". A real page goes through several stages:
+//
+//   Loading ──dom ready──> Interactive ──WELoad(true)──> Complete
+//      │                        │                           │
+//      └───────────── WELoad(false) (unload) ───────────────┴──> Unloaded ──navigate──> Loading
+//
+// and any stage before Unloaded can fail and move to Error, which only leaves by navigating
+// to a new page. WELoad(true) may also jump straight from Loading to Complete, and from
+// Unloaded too: a recording of a reload is WELoad(false) then WELoad(true), with nothing in
+// between to say the page started loading again.
+//
+// PageLifecycle follows those stages as events arrive and refuses anything that doesn't fit:
+// loading a page that is already complete, or a click that arrives before the page finished
+// loading (a user can't click a button that isn't there yet, so a recording with that click
+// in it is broken). Hooks run on every transition, for logging or to start work once the
+// page is ready.
+
+use std::fmt;
+
+use super::{TimedEvent, Timestamp, WebEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PageState { Loading, Interactive, Complete, Unloaded, Error }
+
+impl PageState {
+  pub fn name(&self) -> &'static str {
+    match self {
+      PageState::Loading => "loading",
+      PageState::Interactive => "interactive",
+      PageState::Complete => "complete",
+      PageState::Unloaded => "unloaded",
+      PageState::Error => "error",
+    }
+  }
+}
+
+impl fmt::Display for PageState {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+// What can move the page to another state. Load and Unload come from WELoad events; the
+// others have no WebEvent and are sent by whoever drives the page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+  DomReady,
+  Load,
+  Unload,
+  Fail(String),
+  Navigate,
+}
+
+impl Signal {
+  pub fn from_event(event: &WebEvent) -> Option<Signal> {
+    match event {
+      WebEvent::WELoad(true) => Some(Signal::Load),
+      WebEvent::WELoad(false) => Some(Signal::Unload),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for Signal {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Signal::DomReady => write!(f, "dom ready"),
+      Signal::Load => write!(f, "load (WELoad(true))"),
+      Signal::Unload => write!(f, "unload (WELoad(false))"),
+      Signal::Fail(reason) => write!(f, "failure '{}'", reason),
+      Signal::Navigate => write!(f, "navigate"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+  pub from: PageState,
+  pub to: PageState,
+  pub signal: Signal,
+  // When the event behind it happened, if it came with a timestamp
+  pub at: Option<Timestamp>,
+}
+
+impl fmt::Display for Transition {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} -> {} on {}", self.from, self.to, self.signal)?;
+    if let Some(at) = self.at {
+      write!(f, " at {}ms", at.as_millis())?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleError {
+  InvalidTransition { state: PageState, signal: Signal, at: Option<Timestamp> },
+  InputBeforeLoad { state: PageState, event: WebEvent, at: Option<Timestamp> },
+  InputAfterUnload { event: WebEvent, at: Option<Timestamp> },
+  InputAfterError { event: WebEvent, reason: String, at: Option<Timestamp> },
+}
+
+fn write_at(f: &mut fmt::Formatter, at: &Option<Timestamp>) -> fmt::Result {
+  match at {
+    Some(at) => write!(f, " (at {}ms)", at.as_millis()),
+    None => Ok(()),
+  }
+}
+
+impl fmt::Display for LifecycleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      LifecycleError::InvalidTransition { state, signal, at } => {
+        write!(f, "a {} page can't handle {}", state, signal)?;
+        write_at(f, at)
+      },
+      LifecycleError::InputBeforeLoad { state, event, at } => {
+        write!(f, "'{}' arrived while the page was still {}, input is only accepted after WELoad(true)", event, state)?;
+        write_at(f, at)
+      },
+      LifecycleError::InputAfterUnload { event, at } => {
+        write!(f, "'{}' arrived after the page was unloaded", event)?;
+        write_at(f, at)
+      },
+      LifecycleError::InputAfterError { event, reason, at } => {
+        write!(f, "'{}' arrived after the page failed to load: {}", event, reason)?;
+        write_at(f, at)
+      },
+    }
+  }
+}
+
+impl std::error::Error for LifecycleError {}
+
+// The state a signal moves to from `state`, or None when the move isn't allowed
+fn next_state(state: PageState, signal: &Signal) -> Option<PageState> {
+  use PageState::*;
+  match (state, signal) {
+    (Loading, Signal::DomReady) => Some(Interactive),
+    (Loading | Interactive, Signal::Load) => Some(Complete),
+    (Loading | Interactive | Complete, Signal::Unload) => Some(Unloaded),
+    (Loading | Interactive | Complete, Signal::Fail(_)) => Some(Error),
+    (Unloaded | Error, Signal::Navigate) => Some(Loading),
+    // A reload, loading the same page again in one step
+    (Unloaded, Signal::Load) => Some(Complete),
+    _ => None,
+  }
+}
+
+type Hook = Box<dyn FnMut(&Transition)>;
+
+pub struct PageLifecycle {
+  state: PageState,
+  // Why the page is in the Error state
+  failure: Option<String>,
+  // Browsers let people type into a page that is still loading images. Off by default,
+  // which only accepts input once the page is Complete.
+  pub input_while_interactive: bool,
+  hooks: Vec<(Option<PageState>, Hook)>,
+}
+
+impl Default for PageLifecycle {
+  fn default() -> PageLifecycle {
+    PageLifecycle::new()
+  }
+}
+
+impl fmt::Debug for PageLifecycle {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("PageLifecycle")
+      .field("state", &self.state)
+      .field("failure", &self.failure)
+      .field("input_while_interactive", &self.input_while_interactive)
+      .field("hooks", &self.hooks.len())
+      .finish()
+  }
+}
+
+impl PageLifecycle {
+  // A page that has just started loading
+  pub fn new() -> PageLifecycle {
+    PageLifecycle { state: PageState::Loading, failure: None, input_while_interactive: false, hooks: Vec::new() }
+  }
+
+  pub fn state(&self) -> PageState {
+    self.state
+  }
+
+  pub fn failure(&self) -> Option<&str> {
+    self.failure.as_deref()
+  }
+
+  // Run `hook` after every transition
+  pub fn on_transition<F>(&mut self, hook: F)
+  where
+    F: FnMut(&Transition) + 'static,
+  {
+    self.hooks.push((None, Box::new(hook)));
+  }
+
+  // Run `hook` after every transition into `state`
+  pub fn on_enter<F>(&mut self, state: PageState, hook: F)
+  where
+    F: FnMut(&Transition) + 'static,
+  {
+    self.hooks.push((Some(state), Box::new(hook)));
+  }
+
+  pub fn signal(&mut self, signal: Signal, at: Option<Timestamp>) -> Result<Transition, LifecycleError> {
+    let to = match next_state(self.state, &signal) {
+      Some(to) => to,
+      None => return Err(LifecycleError::InvalidTransition { state: self.state, signal, at }),
+    };
+    self.failure = match &signal {
+      Signal::Fail(reason) => Some(reason.clone()),
+      _ => None,
+    };
+    let transition = Transition { from: self.state, to, signal, at };
+    self.state = to;
+    for (state, hook) in self.hooks.iter_mut() {
+      if state.is_none_or(|state| state == to) {
+        hook(&transition);
+      }
+    }
+    Ok(transition)
+  }
+
+  pub fn dom_ready(&mut self) -> Result<Transition, LifecycleError> {
+    self.signal(Signal::DomReady, None)
+  }
+
+  pub fn fail(&mut self, reason: &str) -> Result<Transition, LifecycleError> {
+    self.signal(Signal::Fail(reason.to_string()), None)
+  }
+
+  pub fn navigate(&mut self) -> Result<Transition, LifecycleError> {
+    self.signal(Signal::Navigate, None)
+  }
+
+  // WELoad events move the page along and return the transition. Clicks and keys don't
+  // change the state, they are only checked: Ok(None) means the page accepts them.
+  pub fn handle(&mut self, event: &WebEvent) -> Result<Option<Transition>, LifecycleError> {
+    self.handle_at(event, None)
+  }
+
+  pub fn handle_timed(&mut self, event: &TimedEvent) -> Result<Option<Transition>, LifecycleError> {
+    self.handle_at(&event.event, Some(event.timestamp))
+  }
+
+  fn handle_at(&mut self, event: &WebEvent, at: Option<Timestamp>) -> Result<Option<Transition>, LifecycleError> {
+    if let Some(signal) = Signal::from_event(event) {
+      return self.signal(signal, at).map(Some);
+    }
+    let event = *event;
+    match self.state {
+      PageState::Complete => Ok(None),
+      PageState::Interactive if self.input_while_interactive => Ok(None),
+      PageState::Loading | PageState::Interactive => Err(LifecycleError::InputBeforeLoad { state: self.state, event, at }),
+      PageState::Unloaded => Err(LifecycleError::InputAfterUnload { event, at }),
+      PageState::Error => {
+        let reason = self.failure.clone().unwrap_or_default();
+        Err(LifecycleError::InputAfterError { event, reason, at })
+      },
+    }
+  }
+}
@@ -0,0 +1,528 @@
+// ======================== EVENT QUERIES ========================
+// A recording can hold thousands of events. Instead of writing a closure every time we want
+// "the clicks in the top-left corner", queries are written as short text:
+//
+//   click where x > 100 and y < 300
+//   keys where key = 'N' and mods contains Ctrl
+//   * where target = 'search' or (time >= 1700000000000 and not kind = load)
+//
+// The first word is the event kind (load, click, keys, or * for any), optionally followed
+// by `where` and conditions joined with and, or, not and parentheses.
+//
+// Fields and the operators they accept:
+//   > target, the element id as text: = != contains
+//   > time in milliseconds: = != < <= > >=
+//   > kind (load, click or keys): = !=
+//   > loaded (true or false), load events only: = !=
+//   > x and y, click events only: = != < <= > >=
+//   > button (left, middle, right, back, forward), click events only: = !=
+//   > key (N, Enter, F4...), keys events only: = !=
+//   > mods (Ctrl+Shift, or none), click and keys events: = != contains
+//
+// A query is checked once when it is parsed, so a typo or "x > 'abc'" is an error instead of
+// a query that silently matches nothing. Under `*` a field that an event doesn't have (the x
+// of a key press) makes that comparison false.
+
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+use super::bus::EventFilter;
+use super::{EventKind, Key, Modifiers, MouseButton, TimedEvent, WebEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field { Target, Time, Kind, Loaded, X, Y, Button, Key, Mods }
+
+impl Field {
+  pub const ALL: [Field; 9] = [
+    Field::Target, Field::Time, Field::Kind, Field::Loaded, Field::X, Field::Y, Field::Button, Field::Key, Field::Mods,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Field::Target => "target",
+      Field::Time => "time",
+      Field::Kind => "kind",
+      Field::Loaded => "loaded",
+      Field::X => "x",
+      Field::Y => "y",
+      Field::Button => "button",
+      Field::Key => "key",
+      Field::Mods => "mods",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Field> {
+    Field::ALL.iter().copied().find(|field| field.name().eq_ignore_ascii_case(name))
+  }
+
+  // Whether events of this kind have the field
+  pub fn applies_to(&self, kind: EventKind) -> bool {
+    match self {
+      Field::Target | Field::Time | Field::Kind => true,
+      Field::Loaded => kind == EventKind::Load,
+      Field::X | Field::Y | Field::Button => kind == EventKind::Click,
+      Field::Key => kind == EventKind::Keys,
+      Field::Mods => kind != EventKind::Load,
+    }
+  }
+
+  fn accepts(&self, op: Op) -> bool {
+    match self {
+      Field::Time | Field::X | Field::Y => op != Op::Contains,
+      Field::Target | Field::Mods => matches!(op, Op::Eq | Op::Ne | Op::Contains),
+      Field::Kind | Field::Loaded | Field::Button | Field::Key => matches!(op, Op::Eq | Op::Ne),
+    }
+  }
+
+  fn parse_value(&self, text: &str) -> Option<Value> {
+    match self {
+      Field::Target => Some(Value::Text(text.to_string())),
+      Field::Time | Field::X | Field::Y => text.parse().ok().map(Value::Number),
+      Field::Kind => EventKind::from_name(&text.to_ascii_lowercase()).map(Value::Kind),
+      Field::Loaded => text.to_ascii_lowercase().parse().ok().map(Value::Bool),
+      Field::Button => button_named(text).map(Value::Button),
+      Field::Key => text.parse::<Key>().ok().map(Value::Key),
+      Field::Mods => text.parse::<Modifiers>().ok().map(Value::Mods),
+    }
+  }
+
+  fn value_of(&self, event: &WebEvent, meta: Option<&TimedEvent>) -> Option<Value> {
+    let value = match (self, event) {
+      (Field::Target, _) => Value::Text(meta?.target.as_str().to_string()),
+      (Field::Time, _) => Value::Number(i64::try_from(meta?.timestamp.as_millis()).ok()?),
+      (Field::Kind, _) => Value::Kind(event.kind()),
+      (Field::Loaded, WebEvent::WELoad(loaded)) => Value::Bool(*loaded),
+      (Field::X, WebEvent::WEClick(click)) => Value::Number(click.x),
+      (Field::Y, WebEvent::WEClick(click)) => Value::Number(click.y),
+      (Field::Button, WebEvent::WEClick(click)) => Value::Button(click.button),
+      // Parsed keys are uppercase, so a query for 'n' finds a recorded 'n' too
+      (Field::Key, WebEvent::WEKeys(press)) => Value::Key(press.key().normalized()),
+      (Field::Mods, WebEvent::WEClick(click)) => Value::Mods(click.modifiers),
+      (Field::Mods, WebEvent::WEKeys(press)) => Value::Mods(press.modifiers()),
+      _ => return None,
+    };
+    Some(value)
+  }
+
+  // The field's value as text, for grouping events by it
+  pub fn text_of(&self, event: &TimedEvent) -> Option<String> {
+    self.value_of(&event.event, Some(event)).map(|value| value.to_string())
+  }
+}
+
+impl fmt::Display for Field {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+fn button_named(name: &str) -> Option<MouseButton> {
+  match name.to_ascii_lowercase().as_str() {
+    "left" => Some(MouseButton::Left),
+    "middle" => Some(MouseButton::Middle),
+    "right" => Some(MouseButton::Right),
+    "back" => Some(MouseButton::Back),
+    "forward" => Some(MouseButton::Forward),
+    _ => None,
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op { Eq, Ne, Lt, Le, Gt, Ge, Contains }
+
+impl fmt::Display for Op {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let symbol = match self {
+      Op::Eq => "=",
+      Op::Ne => "!=",
+      Op::Lt => "<",
+      Op::Le => "<=",
+      Op::Gt => ">",
+      Op::Ge => ">=",
+      Op::Contains => "contains",
+    };
+    write!(f, "{}", symbol)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+  Number(i64),
+  Bool(bool),
+  Text(String),
+  Kind(EventKind),
+  Button(MouseButton),
+  Key(Key),
+  Mods(Modifiers),
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Value::Number(number) => write!(f, "{}", number),
+      Value::Bool(flag) => write!(f, "{}", flag),
+      Value::Text(text) => write!(f, "{}", text),
+      Value::Kind(kind) => write!(f, "{}", kind.name()),
+      Value::Button(button) => write!(f, "{}", format!("{:?}", button).to_lowercase()),
+      Value::Key(key) => write!(f, "{}", key),
+      Value::Mods(modifiers) => write!(f, "{}", modifiers),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+  pub field: Field,
+  pub op: Op,
+  pub value: Value,
+}
+
+impl Comparison {
+  fn matches(&self, event: &WebEvent, meta: Option<&TimedEvent>) -> bool {
+    let actual = match self.field.value_of(event, meta) {
+      Some(actual) => actual,
+      None => return false,
+    };
+    match (self.op, &actual, &self.value) {
+      (Op::Eq, actual, expected) => actual == expected,
+      (Op::Ne, actual, expected) => actual != expected,
+      (Op::Contains, Value::Mods(held), Value::Mods(wanted)) => held.contains(*wanted),
+      (Op::Contains, Value::Text(text), Value::Text(wanted)) => text.contains(wanted.as_str()),
+      (op, Value::Number(actual), Value::Number(expected)) => match op {
+        Op::Lt => actual < expected,
+        Op::Le => actual <= expected,
+        Op::Gt => actual > expected,
+        Op::Ge => actual >= expected,
+        _ => false,
+      },
+      _ => false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+  Compare(Comparison),
+  Not(Box<Condition>),
+  And(Box<Condition>, Box<Condition>),
+  Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+  fn matches(&self, event: &WebEvent, meta: Option<&TimedEvent>) -> bool {
+    match self {
+      Condition::Compare(comparison) => comparison.matches(event, meta),
+      Condition::Not(inner) => !inner.matches(event, meta),
+      Condition::And(left, right) => left.matches(event, meta) && right.matches(event, meta),
+      Condition::Or(left, right) => left.matches(event, meta) || right.matches(event, meta),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+  Empty,
+  UnexpectedEnd { expected: &'static str },
+  Unexpected { position: usize, found: String, expected: &'static str },
+  UnterminatedString { position: usize },
+  UnknownKind { position: usize, name: String },
+  UnknownField { position: usize, name: String },
+  FieldNotOnKind { position: usize, field: Field, kind: EventKind },
+  InvalidOperator { position: usize, field: Field, op: Op },
+  InvalidValue { position: usize, field: Field, value: String },
+}
+
+impl fmt::Display for QueryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      QueryError::Empty => write!(f, "the query is empty, start it with load, click, keys or *"),
+      QueryError::UnexpectedEnd { expected } => write!(f, "the query ends too early, expected {}", expected),
+      QueryError::Unexpected { position, found, expected } => {
+        write!(f, "column {}: expected {} but found '{}'", position + 1, expected, found)
+      },
+      QueryError::UnterminatedString { position } => write!(f, "column {}: the quoted text is never closed", position + 1),
+      QueryError::UnknownKind { position, name } => {
+        write!(f, "column {}: unknown event kind '{}', expected load, click, keys or *", position + 1, name)
+      },
+      QueryError::UnknownField { position, name } => {
+        let names: Vec<&str> = Field::ALL.iter().map(|field| field.name()).collect();
+        write!(f, "column {}: unknown field '{}', expected one of {}", position + 1, name, names.join(", "))
+      },
+      QueryError::FieldNotOnKind { position, field, kind } => {
+        write!(f, "column {}: {} events have no '{}' field", position + 1, kind.name(), field)
+      },
+      QueryError::InvalidOperator { position, field, op } => {
+        write!(f, "column {}: '{}' can't be compared with {}", position + 1, field, op)
+      },
+      QueryError::InvalidValue { position, field, value } => {
+        write!(f, "column {}: '{}' is not a valid {}", position + 1, value, field)
+      },
+    }
+  }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+  // A bare word: a kind, field, keyword, number or unquoted value like Ctrl+Shift
+  Word(String),
+  // Text in single or double quotes
+  Quoted(String),
+  Op(Op),
+  Open,
+  Close,
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Token::Word(word) => write!(f, "{}", word),
+      Token::Quoted(text) => write!(f, "'{}'", text),
+      Token::Op(op) => write!(f, "{}", op),
+      Token::Open => write!(f, "("),
+      Token::Close => write!(f, ")"),
+    }
+  }
+}
+
+// Split the query into tokens, each with the character column it starts at
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+  let chars: Vec<char> = text.chars().collect();
+  let mut tokens = Vec::new();
+  let mut index = 0;
+  while index < chars.len() {
+    let start = index;
+    let c = chars[index];
+    let next = chars.get(index + 1).copied();
+    let token = match c {
+      _ if c.is_whitespace() => {
+        index += 1;
+        continue;
+      },
+      '(' => Token::Open,
+      ')' => Token::Close,
+      '=' if next == Some('=') => {
+        index += 1;
+        Token::Op(Op::Eq)
+      },
+      '=' => Token::Op(Op::Eq),
+      '!' if next == Some('=') => {
+        index += 1;
+        Token::Op(Op::Ne)
+      },
+      '<' | '>' => {
+        let or_equal = next == Some('=');
+        if or_equal {
+          index += 1;
+        }
+        Token::Op(match (c, or_equal) {
+          ('<', false) => Op::Lt,
+          ('<', true) => Op::Le,
+          ('>', false) => Op::Gt,
+          _ => Op::Ge,
+        })
+      },
+      '\'' | '"' => {
+        let end = chars[index + 1..].iter().position(|&other| other == c)
+          .ok_or(QueryError::UnterminatedString { position: start })?;
+        let quoted: String = chars[index + 1..index + 1 + end].iter().collect();
+        index += end + 1;
+        Token::Quoted(quoted)
+      },
+      _ => {
+        while index + 1 < chars.len() && !is_word_end(chars[index + 1]) {
+          index += 1;
+        }
+        Token::Word(chars[start..=index].iter().collect())
+      },
+    };
+    index += 1;
+    tokens.push((start, token));
+  }
+  Ok(tokens)
+}
+
+fn is_word_end(c: char) -> bool {
+  c.is_whitespace() || "()=!<>'\"".contains(c)
+}
+
+struct Parser {
+  tokens: Vec<(usize, Token)>,
+  next: usize,
+  kind: Option<EventKind>,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.next).map(|(_, token)| token)
+  }
+
+  fn advance(&mut self, expected: &'static str) -> Result<(usize, Token), QueryError> {
+    let token = self.tokens.get(self.next).cloned().ok_or(QueryError::UnexpectedEnd { expected })?;
+    self.next += 1;
+    Ok(token)
+  }
+
+  fn peek_keyword(&self, keyword: &str) -> bool {
+    matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+  }
+
+  fn or(&mut self) -> Result<Condition, QueryError> {
+    let mut condition = self.and()?;
+    while self.peek_keyword("or") {
+      self.next += 1;
+      condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+    }
+    Ok(condition)
+  }
+
+  fn and(&mut self) -> Result<Condition, QueryError> {
+    let mut condition = self.not()?;
+    while self.peek_keyword("and") {
+      self.next += 1;
+      condition = Condition::And(Box::new(condition), Box::new(self.not()?));
+    }
+    Ok(condition)
+  }
+
+  fn not(&mut self) -> Result<Condition, QueryError> {
+    if self.peek_keyword("not") {
+      self.next += 1;
+      return Ok(Condition::Not(Box::new(self.not()?)));
+    }
+    if self.peek() == Some(&Token::Open) {
+      self.next += 1;
+      let condition = self.or()?;
+      return match self.advance("')'")? {
+        (_, Token::Close) => Ok(condition),
+        (position, found) => Err(QueryError::Unexpected { position, found: found.to_string(), expected: "')'" }),
+      };
+    }
+    self.comparison().map(Condition::Compare)
+  }
+
+  fn comparison(&mut self) -> Result<Comparison, QueryError> {
+    let field = match self.advance("a field")? {
+      (position, Token::Word(name)) => {
+        let field = Field::from_name(&name).ok_or(QueryError::UnknownField { position, name })?;
+        match self.kind {
+          Some(kind) if !field.applies_to(kind) => return Err(QueryError::FieldNotOnKind { position, field, kind }),
+          _ => field,
+        }
+      },
+      (position, found) => return Err(QueryError::Unexpected { position, found: found.to_string(), expected: "a field" }),
+    };
+
+    let op = match self.advance("an operator")? {
+      (_, Token::Op(op)) => op,
+      (_, Token::Word(word)) if word.eq_ignore_ascii_case("contains") => Op::Contains,
+      (position, found) => {
+        return Err(QueryError::Unexpected { position, found: found.to_string(), expected: "an operator" });
+      },
+    };
+    if !field.accepts(op) {
+      return Err(QueryError::InvalidOperator { position: self.tokens[self.next - 1].0, field, op });
+    }
+
+    let value = match self.advance("a value")? {
+      (position, Token::Word(text)) | (position, Token::Quoted(text)) => {
+        field.parse_value(&text).ok_or(QueryError::InvalidValue { position, field, value: text })?
+      },
+      (position, found) => return Err(QueryError::Unexpected { position, found: found.to_string(), expected: "a value" }),
+    };
+    Ok(Comparison { field, op, value })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+  // None matches every kind
+  pub kind: Option<EventKind>,
+  pub condition: Option<Condition>,
+  source: String,
+}
+
+impl Query {
+  pub fn parse(text: &str) -> Result<Query, QueryError> {
+    let tokens = tokenize(text)?;
+    let (position, first) = tokens.first().cloned().ok_or(QueryError::Empty)?;
+    let kind = match first {
+      Token::Word(word) if word == "*" || word.eq_ignore_ascii_case("any") => None,
+      Token::Word(word) => match EventKind::from_name(&word.to_ascii_lowercase()) {
+        Some(kind) => Some(kind),
+        None => return Err(QueryError::UnknownKind { position, name: word }),
+      },
+      found => return Err(QueryError::Unexpected { position, found: found.to_string(), expected: "an event kind" }),
+    };
+
+    let mut parser = Parser { tokens, next: 1, kind };
+    let condition = if parser.peek().is_none() {
+      None
+    } else if parser.peek_keyword("where") {
+      parser.next += 1;
+      Some(parser.or()?)
+    } else {
+      let (position, found) = parser.advance("'where'")?;
+      return Err(QueryError::Unexpected { position, found: found.to_string(), expected: "'where'" });
+    };
+    if let Ok((position, found)) = parser.advance("") {
+      return Err(QueryError::Unexpected { position, found: found.to_string(), expected: "'and', 'or' or the end" });
+    }
+    Ok(Query { kind, condition, source: text.trim().to_string() })
+  }
+
+  // Only the event itself, so target and time comparisons are false
+  pub fn matches_event(&self, event: &WebEvent) -> bool {
+    self.check(event, None)
+  }
+
+  pub fn matches(&self, event: &TimedEvent) -> bool {
+    self.check(&event.event, Some(event))
+  }
+
+  fn check(&self, event: &WebEvent, meta: Option<&TimedEvent>) -> bool {
+    if self.kind.is_some_and(|kind| kind != event.kind()) {
+      return false;
+    }
+    self.condition.as_ref().is_none_or(|condition| condition.matches(event, meta))
+  }
+
+  // Subscribe to an EventBus with a query instead of a closure
+  pub fn into_filter(self) -> EventFilter {
+    EventFilter::Predicate(Box::new(move |event| self.matches(event)))
+  }
+}
+
+impl FromStr for Query {
+  type Err = QueryError;
+
+  fn from_str(text: &str) -> Result<Query, QueryError> {
+    Query::parse(text)
+  }
+}
+
+impl fmt::Display for Query {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.source)
+  }
+}
+
+// How many events share each value of `field`, most common first.
+// Events without the field are left out.
+pub fn group_by<'a, I>(events: I, field: Field) -> Vec<(String, usize)>
+where
+  I: IntoIterator<Item = &'a TimedEvent>,
+{
+  let mut groups: Vec<(String, usize)> = Vec::new();
+  for value in events.into_iter().filter_map(|event| field.text_of(event)) {
+    match groups.iter_mut().find(|(existing, _)| *existing == value) {
+      Some((_, count)) => *count += 1,
+      None => groups.push((value, 1)),
+    }
+  }
+  // A stable sort keeps the order values first appeared in when counts tie
+  groups.sort_by_key(|(_, count)| Reverse(*count));
+  groups
+}
@@ -0,0 +1,153 @@
+// ======================== RECORD AND REPLAY ========================
+// When a tester hits a bug we want the exact events that led to it. A Recorder writes
+// TimedEvent values to a file as they happen (in either codec format), and replay feeds
+// them back later in the same order:
+//
+//   > Pacing::Unpaced sends them one after the other as fast as possible
+//   > Pacing::Original waits between events like the tester did, divided by a speed factor
+//     (2.0 replays twice as fast, 0.5 at half speed)
+//
+// Waits are measured from the start of the replay, not from the previous event, so small
+// delays in the handlers don't add up over a long recording.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::bus::{Delivery, EventBus};
+use super::codec::{self, CodecError};
+use super::{TargetId, TimedEvent, WebEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat { JsonLines, Binary }
+
+impl RecordingFormat {
+  // Files ending in .bin are binary, everything else is JSON Lines
+  pub fn for_path(path: &Path) -> RecordingFormat {
+    match path.extension().and_then(|extension| extension.to_str()) {
+      Some("bin") => RecordingFormat::Binary,
+      _ => RecordingFormat::JsonLines,
+    }
+  }
+}
+
+pub struct Recorder<W: Write> {
+  writer: W,
+  format: RecordingFormat,
+  recorded: usize,
+}
+
+impl<W: Write> Recorder<W> {
+  pub fn new(mut writer: W, format: RecordingFormat) -> io::Result<Recorder<W>> {
+    if format == RecordingFormat::Binary {
+      writer.write_all(&codec::binary_header())?;
+    }
+    Ok(Recorder { writer, format, recorded: 0 })
+  }
+
+  pub fn record(&mut self, event: &TimedEvent) -> Result<(), CodecError> {
+    match self.format {
+      RecordingFormat::JsonLines => writeln!(self.writer, "{}", codec::to_json_line(event))?,
+      RecordingFormat::Binary => self.writer.write_all(&codec::encode_binary_record(event)?)?,
+    }
+    self.recorded += 1;
+    Ok(())
+  }
+
+  // Stamp the event with the current time and record it
+  pub fn record_now(&mut self, target: TargetId, event: WebEvent) -> Result<(), CodecError> {
+    self.record(&TimedEvent::now(target, event))
+  }
+
+  pub fn recorded(&self) -> usize {
+    self.recorded
+  }
+
+  // Flush everything to the writer and hand it back
+  pub fn finish(mut self) -> io::Result<W> {
+    self.writer.flush()?;
+    Ok(self.writer)
+  }
+}
+
+impl Recorder<BufWriter<File>> {
+  pub fn create(path: &Path) -> io::Result<Recorder<BufWriter<File>>> {
+    Recorder::new(BufWriter::new(File::create(path)?), RecordingFormat::for_path(path))
+  }
+}
+
+// Load a recording in either format
+pub fn load(path: &Path) -> Result<Vec<TimedEvent>, CodecError> {
+  codec::read_any(File::open(path)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+  Unpaced,
+  Original { speed: f64 },
+}
+
+impl Pacing {
+  // The speeds worth asking for: slower than 1/100 or faster than 1000 times is never useful
+  pub const MIN_SPEED: f64 = 0.01;
+  pub const MAX_SPEED: f64 = 1000.0;
+
+  // Original pacing at this speed, or None for a speed outside MIN_SPEED..=MAX_SPEED
+  pub fn original(speed: f64) -> Option<Pacing> {
+    if (Pacing::MIN_SPEED..=Pacing::MAX_SPEED).contains(&speed) {
+      Some(Pacing::Original { speed })
+    } else {
+      None
+    }
+  }
+
+  // How long after the first event this one should be sent.
+  // A speed that is zero, negative or not a number can't be waited for, so it is unpaced.
+  // Other speeds built without Pacing::original are held to MIN_SPEED..=MAX_SPEED, so the
+  // wait is at most 100 times the recorded gap and always fits in a Duration.
+  pub fn offset(&self, first: &TimedEvent, event: &TimedEvent) -> Duration {
+    match *self {
+      Pacing::Original { speed } if speed > 0.0 && speed.is_finite() => {
+        let gap = event.timestamp.duration_since(first.timestamp);
+        gap.div_f64(speed.clamp(Pacing::MIN_SPEED, Pacing::MAX_SPEED))
+      },
+      _ => Duration::ZERO,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReplayReport {
+  pub replayed: usize,
+  pub elapsed: Duration,
+}
+
+// Send every event to `sink` in recorded order
+pub fn replay<F>(events: &[TimedEvent], pacing: Pacing, mut sink: F) -> ReplayReport
+where
+  F: FnMut(&TimedEvent),
+{
+  let started = Instant::now();
+  let first = match events.first() {
+    Some(first) => first,
+    None => return ReplayReport::default(),
+  };
+  for event in events {
+    let due = pacing.offset(first, event);
+    let elapsed = started.elapsed();
+    if due > elapsed {
+      thread::sleep(due - elapsed);
+    }
+    sink(event);
+  }
+  ReplayReport { replayed: events.len(), elapsed: started.elapsed() }
+}
+
+// Publish every event on the bus, returning what each publish did
+pub fn replay_into_bus(events: &[TimedEvent], pacing: Pacing, bus: &mut EventBus) -> (ReplayReport, Vec<Delivery>) {
+  let mut deliveries = Vec::with_capacity(events.len());
+  let report = replay(events, pacing, |event| deliveries.push(bus.publish(event)));
+  (report, deliveries)
+}
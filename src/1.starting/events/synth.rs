@@ -0,0 +1,355 @@
+// ======================== SYNTHETIC EVENTS ========================
+// Fuzz and benchmark runs need far more events than anyone wants to record by hand. The
+// EventGenerator makes them up, acting like a (very patient) user:
+//
+//   > the page loads first, and now and then it is reloaded
+//   > clicks land around hotspots (or anywhere on the page), sometimes as double clicks
+//   > words are typed one key at a time with a natural rhythm
+//   > shortcuts from a weighted list are pressed, chords step by step
+//   > between actions the user thinks for a while
+//
+// Everything random comes from one seed: the same seed and config always produce the same
+// events, so a failure found by a fuzz run can be replayed exactly.
+//
+// A config is checked when the generator is made: an action that can be picked must be able
+// to make at least one event, or a generator that only picks it would never return one.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use super::keys::KeyChord;
+use super::{Key, KeyPress, Modifiers, MouseButton, MouseClick, TargetId, TimedEvent, Timestamp, WebEvent};
+
+// SplitMix64: tiny, fast and good enough for test data. Not for anything secret.
+#[derive(Debug, Clone)]
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Rng {
+    Rng { state: seed }
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  // Uniform in [0, 1)
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+
+  // Uniform in [low, high), or low when the range is empty
+  pub fn range(&mut self, low: i64, high: i64) -> i64 {
+    if high <= low {
+      return low;
+    }
+    let span = (high as i128 - low as i128) as u128;
+    (low as i128 + (self.next_u64() as u128 % span) as i128) as i64
+  }
+
+  pub fn chance(&mut self, probability: f64) -> bool {
+    self.next_f64() < probability
+  }
+
+  // Normally distributed, using the Box-Muller transform
+  pub fn normal(&mut self, mean: f64, deviation: f64) -> f64 {
+    let u1 = 1.0 - self.next_f64();
+    let u2 = self.next_f64();
+    mean + deviation * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+  }
+
+  // Exponentially distributed, the time between events that happen at a steady rate
+  pub fn exponential(&mut self, mean: f64) -> f64 {
+    -mean * (1.0 - self.next_f64()).ln()
+  }
+
+  // Index into `weights`, picked in proportion to the weights. None if they are all zero.
+  pub fn weighted(&mut self, weights: &[u32]) -> Option<usize> {
+    let total: u64 = weights.iter().map(|&weight| weight as u64).sum();
+    if total == 0 {
+      return None;
+    }
+    let mut roll = self.next_u64() % total;
+    for (index, &weight) in weights.iter().enumerate() {
+      if roll < weight as u64 {
+        return Some(index);
+      }
+      roll -= weight as u64;
+    }
+    None
+  }
+}
+
+// An area people click a lot, like a toolbar or a submit button
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotspot {
+  pub x: i64,
+  pub y: i64,
+  // Standard deviation of the distance from the centre, in pixels
+  pub spread: f64,
+  pub weight: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClickPositions {
+  Uniform { width: i64, height: i64 },
+  Hotspots(Vec<Hotspot>),
+}
+
+impl ClickPositions {
+  fn sample(&self, rng: &mut Rng) -> (i64, i64) {
+    match self {
+      ClickPositions::Uniform { width, height } => (rng.range(0, *width), rng.range(0, *height)),
+      ClickPositions::Hotspots(spots) => {
+        let weights: Vec<u32> = spots.iter().map(|spot| spot.weight).collect();
+        match rng.weighted(&weights).map(|index| spots[index]) {
+          Some(spot) => (
+            rng.normal(spot.x as f64, spot.spread).round().max(0.0) as i64,
+            rng.normal(spot.y as f64, spot.spread).round().max(0.0) as i64,
+          ),
+          None => (0, 0),
+        }
+      },
+    }
+  }
+}
+
+// Time between key presses while typing, normally distributed and never below `min`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypingSpeed {
+  pub mean: Duration,
+  pub deviation: Duration,
+  pub min: Duration,
+}
+
+impl TypingSpeed {
+  // Roughly 60 words per minute
+  pub const AVERAGE: TypingSpeed = TypingSpeed {
+    mean: Duration::from_millis(200),
+    deviation: Duration::from_millis(60),
+    min: Duration::from_millis(30),
+  };
+
+  fn sample(&self, rng: &mut Rng) -> Duration {
+    let millis = rng.normal(self.mean.as_millis() as f64, self.deviation.as_millis() as f64);
+    Duration::from_millis(millis.max(0.0) as u64).max(self.min)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorConfig {
+  // When the first event happens. Fixed by default so runs don't depend on the real time.
+  pub start: Timestamp,
+  pub targets: Vec<TargetId>,
+  // How often each action is picked
+  pub click_weight: u32,
+  pub typing_weight: u32,
+  pub shortcut_weight: u32,
+  pub reload_weight: u32,
+  pub click_positions: ClickPositions,
+  // Weights for Left, Middle, Right, Back and Forward
+  pub button_weights: [u32; 5],
+  pub double_click_chance: f64,
+  pub typing: TypingSpeed,
+  pub word_length: (usize, usize),
+  pub alphabet: Vec<char>,
+  pub shortcuts: Vec<(KeyChord, u32)>,
+  // Average pause between two actions
+  pub think_time: Duration,
+}
+
+impl Default for GeneratorConfig {
+  fn default() -> GeneratorConfig {
+    let shortcut = |text: &str, weight| (text.parse::<KeyChord>().expect("valid default shortcut"), weight);
+    GeneratorConfig {
+      start: Timestamp::from_millis(1_700_000_000_000),
+      targets: vec![TargetId::new("page")],
+      click_weight: 50,
+      typing_weight: 30,
+      shortcut_weight: 15,
+      reload_weight: 1,
+      click_positions: ClickPositions::Uniform { width: 1280, height: 800 },
+      button_weights: [90, 2, 8, 0, 0],
+      double_click_chance: 0.1,
+      typing: TypingSpeed::AVERAGE,
+      word_length: (2, 9),
+      alphabet: ('a'..='z').collect(),
+      shortcuts: vec![
+        shortcut("Ctrl+C", 10),
+        shortcut("Ctrl+V", 10),
+        shortcut("Ctrl+Z", 5),
+        shortcut("Ctrl+S", 5),
+        shortcut("Ctrl+K Ctrl+C", 1),
+      ],
+      think_time: Duration::from_millis(800),
+    }
+  }
+}
+
+// An action that can be picked but would never make an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+  EmptyAlphabet,
+  NoWordLength,
+  NoShortcuts,
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ConfigError::EmptyAlphabet => write!(f, "typing has a weight but the alphabet is empty"),
+      ConfigError::NoWordLength => write!(f, "typing has a weight but every word is 0 letters long"),
+      ConfigError::NoShortcuts => write!(f, "shortcuts have a weight but none of them can be picked"),
+    }
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl GeneratorConfig {
+  pub fn validate(&self) -> Result<(), ConfigError> {
+    if self.typing_weight > 0 {
+      if self.alphabet.is_empty() {
+        return Err(ConfigError::EmptyAlphabet);
+      }
+      let (shortest, longest) = self.word_length;
+      if longest.max(shortest) == 0 {
+        return Err(ConfigError::NoWordLength);
+      }
+    }
+    let shortcut_picked = self.shortcuts.iter().any(|(chord, weight)| *weight > 0 && !chord.steps().is_empty());
+    if self.shortcut_weight > 0 && !shortcut_picked {
+      return Err(ConfigError::NoShortcuts);
+    }
+    Ok(())
+  }
+}
+
+const BUTTONS: [MouseButton; 5] = [MouseButton::Left, MouseButton::Middle, MouseButton::Right, MouseButton::Back, MouseButton::Forward];
+
+// An endless iterator of events, use .take(n) to get a fixed number of them
+#[derive(Debug, Clone)]
+pub struct EventGenerator {
+  pub config: GeneratorConfig,
+  rng: Rng,
+  now: Timestamp,
+  queued: VecDeque<TimedEvent>,
+}
+
+impl EventGenerator {
+  pub fn new(config: GeneratorConfig, seed: u64) -> Result<EventGenerator, ConfigError> {
+    config.validate()?;
+    let now = config.start;
+    let mut generator = EventGenerator { config, rng: Rng::new(seed), now, queued: VecDeque::new() };
+    let target = generator.target();
+    generator.emit(target, WebEvent::WELoad(true));
+    Ok(generator)
+  }
+
+  fn target(&mut self) -> TargetId {
+    let last = self.config.targets.len() as i64;
+    match self.config.targets.get(self.rng.range(0, last) as usize) {
+      Some(target) => target.clone(),
+      None => TargetId::new("page"),
+    }
+  }
+
+  fn emit(&mut self, target: TargetId, event: WebEvent) {
+    self.queued.push_back(TimedEvent::new(self.now, target, event));
+  }
+
+  fn wait(&mut self, duration: Duration) {
+    self.now = self.now + duration;
+  }
+
+  // Queue the events of one more action
+  fn act(&mut self) {
+    let think = self.rng.exponential(self.config.think_time.as_millis() as f64);
+    self.wait(Duration::from_millis(think as u64));
+    let target = self.target();
+    let weights = [self.config.click_weight, self.config.typing_weight, self.config.shortcut_weight, self.config.reload_weight];
+    match self.rng.weighted(&weights) {
+      Some(0) => self.click(target),
+      Some(1) => self.type_word(target),
+      Some(2) => self.shortcut(target),
+      Some(3) => {
+        self.emit(target.clone(), WebEvent::WELoad(false));
+        let reload = self.rng.range(100, 1500);
+        self.wait(Duration::from_millis(reload as u64));
+        self.emit(target, WebEvent::WELoad(true));
+      },
+      // Every action weighs zero: the user just sits there, so keep the page loaded
+      _ => self.emit(target, WebEvent::WELoad(true)),
+    }
+  }
+
+  fn click(&mut self, target: TargetId) {
+    let (x, y) = self.config.click_positions.sample(&mut self.rng);
+    let button = self.rng.weighted(&self.config.button_weights).map(|index| BUTTONS[index]).unwrap_or(MouseButton::Left);
+    let click = MouseClick { x, y, button, modifiers: Modifiers::NONE };
+    self.emit(target.clone(), WebEvent::WEClick(click));
+    if self.rng.chance(self.config.double_click_chance) {
+      let gap = self.rng.range(60, 250);
+      self.wait(Duration::from_millis(gap as u64));
+      self.emit(target, WebEvent::WEClick(click));
+    }
+  }
+
+  fn type_word(&mut self, target: TargetId) {
+    let (shortest, longest) = self.config.word_length;
+    let length = self.rng.range(shortest as i64, longest.max(shortest) as i64 + 1);
+    for index in 0..length {
+      if index > 0 {
+        let gap = self.config.typing.sample(&mut self.rng);
+        self.wait(gap);
+      }
+      let letters = self.config.alphabet.len() as i64;
+      let letter = match self.config.alphabet.get(self.rng.range(0, letters) as usize) {
+        Some(letter) => *letter,
+        None => return,
+      };
+      // Keys are named by the key, so an uppercase letter is Shift plus that key
+      let modifiers = if letter.is_uppercase() { Modifiers::SHIFT } else { Modifiers::NONE };
+      let key = Key::Char(letter.to_uppercase().next().unwrap_or(letter));
+      self.emit(target.clone(), WebEvent::WEKeys(KeyPress(modifiers, key)));
+    }
+  }
+
+  fn shortcut(&mut self, target: TargetId) {
+    let weights: Vec<u32> = self.config.shortcuts.iter().map(|(_, weight)| *weight).collect();
+    let chord = match self.rng.weighted(&weights) {
+      Some(index) => self.config.shortcuts[index].0.clone(),
+      None => return,
+    };
+    for (index, press) in chord.steps().iter().enumerate() {
+      if index > 0 {
+        let gap = self.config.typing.sample(&mut self.rng);
+        self.wait(gap);
+      }
+      self.emit(target.clone(), WebEvent::WEKeys(*press));
+    }
+  }
+}
+
+impl Iterator for EventGenerator {
+  type Item = TimedEvent;
+
+  fn next(&mut self) -> Option<TimedEvent> {
+    while self.queued.is_empty() {
+      self.act();
+    }
+    self.queued.pop_front()
+  }
+}
+
+// `count` events from a fresh generator
+pub fn generate(config: GeneratorConfig, seed: u64, count: usize) -> Result<Vec<TimedEvent>, ConfigError> {
+  Ok(EventGenerator::new(config, seed)?.take(count).collect())
+}
@@ -0,0 +1,569 @@
+// ======================== DEBOUNCE, THROTTLE AND COALESCE ========================
+// Input arrives in bursts: a shaky double click sends the same click twice, holding a key
+// down repeats it thirty times a second. Three operators tame those bursts:
+//
+//   > Debounce waits until events stop for `window` and then lets only the last one through
+//     (search-as-you-type: only look up what was typed once the typing pauses)
+//   > Throttle lets one event through and drops the rest until `interval` has passed
+//     (rate-limit key repeat)
+//   > Coalesce merges consecutive events that are "the same" into the first one, for
+//     example clicks within 50 ms of each other at the same position
+//
+// Each operator only looks at the events its `only` kind selects (all of them by default);
+// other events pass straight through and may overtake an event the operator is holding.
+//
+// The operators themselves are small state machines, so the same code runs in two places:
+//   > on an Iterator of TimedEvent, where time is read from the events' own timestamps
+//   > on an EventStream, the async version of an iterator, where time comes from a Clock.
+//     TestClock only moves when told to, which makes timing behaviour deterministic in tests.
+// Either way an operator is told the time with every call and never looks at timestamps
+// itself, so a stream's windows are measured on its clock from start to end.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::Duration;
+
+use super::{EventKind, TimedEvent, Timestamp, WebEvent};
+
+pub trait Operator {
+  // Take an event that arrived at `now`, returning the events that are ready to go out because of it
+  fn push(&mut self, event: TimedEvent, now: Timestamp) -> Vec<TimedEvent>;
+  // Release anything that has waited long enough by `now`
+  fn poll_due(&mut self, now: Timestamp) -> Vec<TimedEvent>;
+  // When poll_due will next have something to release
+  fn next_deadline(&self) -> Option<Timestamp>;
+  // The input ended: release everything still held
+  fn flush(&mut self) -> Vec<TimedEvent>;
+}
+
+fn selected(only: Option<EventKind>, event: &TimedEvent) -> bool {
+  only.is_none_or(|kind| kind == event.event.kind())
+}
+
+#[derive(Debug, Clone)]
+pub struct Debounce {
+  pub window: Duration,
+  pub only: Option<EventKind>,
+  // The held event and when it arrived
+  pending: Option<(Timestamp, TimedEvent)>,
+}
+
+impl Debounce {
+  pub fn new(window: Duration) -> Debounce {
+    Debounce { window, only: None, pending: None }
+  }
+
+  pub fn only(mut self, kind: EventKind) -> Debounce {
+    self.only = Some(kind);
+    self
+  }
+}
+
+impl Operator for Debounce {
+  fn push(&mut self, event: TimedEvent, now: Timestamp) -> Vec<TimedEvent> {
+    if !selected(self.only, &event) {
+      return vec![event];
+    }
+    // A pending event that waited long enough goes out, one that didn't is dropped
+    let ready = self.poll_due(now);
+    self.pending = Some((now, event));
+    ready
+  }
+
+  fn poll_due(&mut self, now: Timestamp) -> Vec<TimedEvent> {
+    match self.next_deadline() {
+      Some(deadline) if now >= deadline => self.flush(),
+      _ => Vec::new(),
+    }
+  }
+
+  fn next_deadline(&self) -> Option<Timestamp> {
+    self.pending.as_ref().map(|(arrived, _)| *arrived + self.window)
+  }
+
+  fn flush(&mut self) -> Vec<TimedEvent> {
+    self.pending.take().map(|(_, event)| event).into_iter().collect()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Throttle {
+  pub interval: Duration,
+  pub only: Option<EventKind>,
+  last_passed: Option<Timestamp>,
+  dropped: usize,
+}
+
+impl Throttle {
+  pub fn new(interval: Duration) -> Throttle {
+    Throttle { interval, only: None, last_passed: None, dropped: 0 }
+  }
+
+  pub fn only(mut self, kind: EventKind) -> Throttle {
+    self.only = Some(kind);
+    self
+  }
+
+  pub fn dropped(&self) -> usize {
+    self.dropped
+  }
+}
+
+impl Operator for Throttle {
+  fn push(&mut self, event: TimedEvent, now: Timestamp) -> Vec<TimedEvent> {
+    if !selected(self.only, &event) {
+      return vec![event];
+    }
+    match self.last_passed {
+      Some(last) if now.duration_since(last) < self.interval => {
+        self.dropped += 1;
+        Vec::new()
+      },
+      _ => {
+        self.last_passed = Some(now);
+        vec![event]
+      },
+    }
+  }
+
+  // Throttle never holds events back, it only drops them
+  fn poll_due(&mut self, _now: Timestamp) -> Vec<TimedEvent> {
+    Vec::new()
+  }
+
+  fn next_deadline(&self) -> Option<Timestamp> {
+    None
+  }
+
+  fn flush(&mut self) -> Vec<TimedEvent> {
+    Vec::new()
+  }
+}
+
+type SameFn = Box<dyn Fn(&TimedEvent, &TimedEvent) -> bool + Send>;
+
+// Merges a run of events into its first one. An event joins the run when `same` says it
+// matches the previous event and it arrived within `window` of it.
+pub struct Coalesce {
+  pub window: Duration,
+  pub only: Option<EventKind>,
+  same: SameFn,
+  // The first and the last event of the current run, and when the last one arrived
+  pending: Option<(TimedEvent, TimedEvent, Timestamp)>,
+  merged: usize,
+}
+
+impl Coalesce {
+  pub fn new<F>(window: Duration, same: F) -> Coalesce
+  where
+    F: Fn(&TimedEvent, &TimedEvent) -> bool + Send + 'static,
+  {
+    Coalesce { window, only: None, same: Box::new(same), pending: None, merged: 0 }
+  }
+
+  // Clicks with the same button, modifiers and position on the same target
+  pub fn clicks(window: Duration) -> Coalesce {
+    Coalesce::new(window, |a, b| {
+      a.target == b.target && matches!((&a.event, &b.event), (WebEvent::WEClick(a), WebEvent::WEClick(b)) if a == b)
+    })
+    .only(EventKind::Click)
+  }
+
+  // Any event that is exactly the same as the one before, on the same target
+  pub fn repeats(window: Duration) -> Coalesce {
+    Coalesce::new(window, |a, b| a.target == b.target && a.event == b.event)
+  }
+
+  pub fn only(mut self, kind: EventKind) -> Coalesce {
+    self.only = Some(kind);
+    self
+  }
+
+  // How many events were merged into an earlier one so far
+  pub fn merged(&self) -> usize {
+    self.merged
+  }
+}
+
+impl Operator for Coalesce {
+  fn push(&mut self, event: TimedEvent, now: Timestamp) -> Vec<TimedEvent> {
+    if !selected(self.only, &event) {
+      return vec![event];
+    }
+    if let Some((_, last, arrived)) = self.pending.as_mut() {
+      if now.duration_since(*arrived) <= self.window && (self.same)(last, &event) {
+        *last = event;
+        *arrived = now;
+        self.merged += 1;
+        return Vec::new();
+      }
+    }
+    let ready = self.flush();
+    self.pending = Some((event.clone(), event, now));
+    ready
+  }
+
+  fn poll_due(&mut self, now: Timestamp) -> Vec<TimedEvent> {
+    match self.next_deadline() {
+      Some(deadline) if now >= deadline => self.flush(),
+      _ => Vec::new(),
+    }
+  }
+
+  // Once nothing could join the run any more. The window is inclusive, so one ms later.
+  fn next_deadline(&self) -> Option<Timestamp> {
+    self.pending.as_ref().map(|(_, _, arrived)| *arrived + self.window + Duration::from_millis(1))
+  }
+
+  fn flush(&mut self) -> Vec<TimedEvent> {
+    self.pending.take().map(|(first, _, _)| first).into_iter().collect()
+  }
+}
+
+// ======================== ITERATORS ========================
+
+pub struct Operated<I, O> {
+  inner: I,
+  operator: O,
+  ready: VecDeque<TimedEvent>,
+  done: bool,
+}
+
+impl<I, O> Operated<I, O> {
+  pub fn operator(&self) -> &O {
+    &self.operator
+  }
+}
+
+impl<I, O> Iterator for Operated<I, O>
+where
+  I: Iterator<Item = TimedEvent>,
+  O: Operator,
+{
+  type Item = TimedEvent;
+
+  fn next(&mut self) -> Option<TimedEvent> {
+    loop {
+      if let Some(event) = self.ready.pop_front() {
+        return Some(event);
+      }
+      if self.done {
+        return None;
+      }
+      match self.inner.next() {
+        Some(event) => {
+          // The events are the clock: whatever was due before this one goes out first
+          let now = event.timestamp;
+          self.ready.extend(self.operator.poll_due(now));
+          self.ready.extend(self.operator.push(event, now));
+        },
+        None => {
+          self.done = true;
+          self.ready.extend(self.operator.flush());
+        },
+      }
+    }
+  }
+}
+
+// events.into_iter().debounce(Duration::from_millis(300))
+pub trait TimingExt: Iterator<Item = TimedEvent> + Sized {
+  fn through<O: Operator>(self, operator: O) -> Operated<Self, O> {
+    Operated { inner: self, operator, ready: VecDeque::new(), done: false }
+  }
+
+  fn debounce(self, window: Duration) -> Operated<Self, Debounce> {
+    self.through(Debounce::new(window))
+  }
+
+  fn throttle(self, interval: Duration) -> Operated<Self, Throttle> {
+    self.through(Throttle::new(interval))
+  }
+
+  fn coalesce_clicks(self, window: Duration) -> Operated<Self, Coalesce> {
+    self.through(Coalesce::clicks(window))
+  }
+}
+
+impl<I: Iterator<Item = TimedEvent>> TimingExt for I {}
+
+// ======================== CLOCKS ========================
+
+pub trait Clock {
+  fn now(&self) -> Timestamp;
+  // Wake `waker` once now() has reached `at`
+  fn wake_at(&self, at: Timestamp, waker: Waker);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Timestamp {
+    Timestamp::now()
+  }
+
+  // No runtime to lean on, so one timer thread, started on first use, does all the waking
+  fn wake_at(&self, at: Timestamp, waker: Waker) {
+    static TIMER: OnceLock<Sender<(Timestamp, Waker)>> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+      let (sender, receiver) = mpsc::channel::<(Timestamp, Waker)>();
+      thread::spawn(move || {
+        let mut sleepers = Vec::new();
+        loop {
+          let now = Timestamp::now();
+          for waker in take_due(&mut sleepers, now) {
+            waker.wake();
+          }
+          let next = sleepers.iter().map(|(at, _): &(Timestamp, Waker)| *at).min();
+          let received = match next {
+            Some(next) => receiver.recv_timeout(next.duration_since(now)),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+          };
+          match received {
+            Ok((at, waker)) => add_sleeper(&mut sleepers, at, waker),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break,
+          }
+        }
+      });
+      sender
+    });
+    let _ = timer.send((at, waker));
+  }
+}
+
+// A stream asks again every time it is polled, so the same task waiting for the same moment
+// keeps one entry instead of piling up copies
+fn add_sleeper(sleepers: &mut Vec<(Timestamp, Waker)>, at: Timestamp, waker: Waker) {
+  match sleepers.iter_mut().find(|(existing_at, existing)| *existing_at == at && existing.will_wake(&waker)) {
+    Some((_, existing)) => *existing = waker,
+    None => sleepers.push((at, waker)),
+  }
+}
+
+// Remove and return the wakers whose time has come
+fn take_due(sleepers: &mut Vec<(Timestamp, Waker)>, now: Timestamp) -> Vec<Waker> {
+  let (due, waiting): (Vec<_>, Vec<_>) = sleepers.drain(..).partition(|(at, _)| *at <= now);
+  *sleepers = waiting;
+  due.into_iter().map(|(_, waker)| waker).collect()
+}
+
+#[derive(Default)]
+struct TestClockState {
+  now: Timestamp,
+  sleepers: Vec<(Timestamp, Waker)>,
+}
+
+// A clock that only moves when advance or set is called. Clones share the same time.
+#[derive(Clone, Default)]
+pub struct TestClock {
+  state: Arc<Mutex<TestClockState>>,
+}
+
+impl TestClock {
+  pub fn new(start: Timestamp) -> TestClock {
+    TestClock { state: Arc::new(Mutex::new(TestClockState { now: start, sleepers: Vec::new() })) }
+  }
+
+  pub fn advance(&self, by: Duration) {
+    let now = self.now() + by;
+    self.set(now);
+  }
+
+  // Move to `now` and wake everything that was waiting for it
+  pub fn set(&self, now: Timestamp) {
+    let due: Vec<Waker> = {
+      let mut state = self.state.lock().unwrap();
+      state.now = now;
+      take_due(&mut state.sleepers, now)
+    };
+    // Wake outside the lock so a woken task can read the clock straight away
+    for waker in due {
+      waker.wake();
+    }
+  }
+}
+
+impl Clock for TestClock {
+  fn now(&self) -> Timestamp {
+    self.state.lock().unwrap().now
+  }
+
+  fn wake_at(&self, at: Timestamp, waker: Waker) {
+    let mut state = self.state.lock().unwrap();
+    if at <= state.now {
+      drop(state);
+      waker.wake();
+    } else {
+      add_sleeper(&mut state.sleepers, at, waker);
+    }
+  }
+}
+
+// ======================== STREAMS ========================
+// The async version of an Iterator: poll_next returns Pending when the next event hasn't
+// arrived yet and wakes the task through the Context once it has. Every stream here is
+// Unpin, so poll_next takes a plain &mut self instead of a pinned one.
+
+pub trait EventStream {
+  fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<TimedEvent>>;
+
+  // stream.next().await
+  fn next(&mut self) -> Next<'_, Self>
+  where
+    Self: Sized,
+  {
+    Next { stream: self }
+  }
+
+  fn through<O: Operator, C: Clock>(self, operator: O, clock: C) -> OperatedStream<Self, O, C>
+  where
+    Self: Sized,
+  {
+    OperatedStream { inner: self, operator, clock, ready: VecDeque::new(), done: false }
+  }
+
+  fn debounce<C: Clock>(self, window: Duration, clock: C) -> OperatedStream<Self, Debounce, C>
+  where
+    Self: Sized,
+  {
+    self.through(Debounce::new(window), clock)
+  }
+
+  fn throttle<C: Clock>(self, interval: Duration, clock: C) -> OperatedStream<Self, Throttle, C>
+  where
+    Self: Sized,
+  {
+    self.through(Throttle::new(interval), clock)
+  }
+
+  fn coalesce_clicks<C: Clock>(self, window: Duration, clock: C) -> OperatedStream<Self, Coalesce, C>
+  where
+    Self: Sized,
+  {
+    self.through(Coalesce::clicks(window), clock)
+  }
+}
+
+pub struct Next<'a, S> {
+  stream: &'a mut S,
+}
+
+impl<S: EventStream> Future for Next<'_, S> {
+  type Output = Option<TimedEvent>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TimedEvent>> {
+    self.stream.poll_next(cx)
+  }
+}
+
+#[derive(Default)]
+struct FeedState {
+  queue: VecDeque<TimedEvent>,
+  closed: bool,
+  waker: Option<Waker>,
+}
+
+// A stream fed by hand (or from another thread) through its FeedSender
+pub struct Feed {
+  state: Arc<Mutex<FeedState>>,
+}
+
+#[derive(Clone)]
+pub struct FeedSender {
+  state: Arc<Mutex<FeedState>>,
+}
+
+impl Feed {
+  pub fn new() -> (FeedSender, Feed) {
+    let state = Arc::new(Mutex::new(FeedState::default()));
+    (FeedSender { state: Arc::clone(&state) }, Feed { state })
+  }
+}
+
+impl FeedSender {
+  pub fn send(&self, event: TimedEvent) {
+    let mut state = self.state.lock().unwrap();
+    state.queue.push_back(event);
+    if let Some(waker) = state.waker.take() {
+      waker.wake();
+    }
+  }
+
+  // End the stream once the queued events are read
+  pub fn close(&self) {
+    let mut state = self.state.lock().unwrap();
+    state.closed = true;
+    if let Some(waker) = state.waker.take() {
+      waker.wake();
+    }
+  }
+}
+
+impl EventStream for Feed {
+  fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<TimedEvent>> {
+    let mut state = self.state.lock().unwrap();
+    match state.queue.pop_front() {
+      Some(event) => Poll::Ready(Some(event)),
+      None if state.closed => Poll::Ready(None),
+      None => {
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+      },
+    }
+  }
+}
+
+pub struct OperatedStream<S, O, C> {
+  inner: S,
+  operator: O,
+  clock: C,
+  ready: VecDeque<TimedEvent>,
+  done: bool,
+}
+
+impl<S, O, C> OperatedStream<S, O, C> {
+  pub fn operator(&self) -> &O {
+    &self.operator
+  }
+}
+
+impl<S: EventStream, O: Operator, C: Clock> EventStream for OperatedStream<S, O, C> {
+  fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<TimedEvent>> {
+    loop {
+      if let Some(event) = self.ready.pop_front() {
+        return Poll::Ready(Some(event));
+      }
+      if self.done {
+        return Poll::Ready(None);
+      }
+      self.ready.extend(self.operator.poll_due(self.clock.now()));
+      if !self.ready.is_empty() {
+        continue;
+      }
+      match self.inner.poll_next(cx) {
+        Poll::Ready(Some(event)) => {
+          let now = self.clock.now();
+          self.ready.extend(self.operator.push(event, now));
+        },
+        Poll::Ready(None) => {
+          self.done = true;
+          self.ready.extend(self.operator.flush());
+        },
+        Poll::Pending => {
+          // Ask to be woken when something held back becomes due, not only on new input
+          if let Some(deadline) = self.operator.next_deadline() {
+            self.clock.wake_at(deadline, cx.waker().clone());
+          }
+          return Poll::Pending;
+        },
+      }
+    }
+  }
+}
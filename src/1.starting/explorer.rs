@@ -0,0 +1,56 @@
+// An interactive companion for variables.rs: instead of reading about the primitive
+// types in comments, we ask the program to show us what they look like in memory.
+//
+// Build:  rustc explorer.rs
+// Usage:  ./explorer int <type> [value] [addend]
+//         ./explorer float <f32|f64> <value> [other]
+//         ./explorer char <text>
+//
+// Examples:
+//   ./explorer int u8            -> u8::MAX + 1, so we can watch it overflow
+//   ./explorer int i8 -128 -1
+//   ./explorer int u32 0xdead_beef
+//   ./explorer float f32 0.1           -> the exact number 0.1f32 really stores
+//   ./explorer float f64 1 1.0000000000000002
+//   ./explorer float f32 0x7fc00001    -> a NaN carrying a payload
+//   ./explorer char 😂
+//   ./explorer char "👍🏽 🇪🇸 한글"          -> bytes vs chars vs graphemes
+
+mod types;
+
+use std::env;
+use std::process;
+
+const USAGE: &str = "usage:
+  explorer int <type> [value] [addend]
+  explorer float <f32|f64> <value> [other]
+  explorer char <text>";
+
+fn run(args: &[String]) -> Result<String, String> {
+  let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+  match args.as_slice() {
+    ["int", type_name, rest @ ..] if rest.len() <= 2 => {
+      let report = types::integers::explore(type_name, rest.first().copied(), rest.get(1).copied())
+        .map_err(|error| error.to_string())?;
+      Ok(report.to_string())
+    },
+    ["float", type_name, value, rest @ ..] if rest.len() <= 1 => {
+      let report = types::floats::explore(type_name, value, rest.first().copied())
+        .map_err(|error| error.to_string())?;
+      Ok(report.to_string())
+    },
+    ["char", words @ ..] if !words.is_empty() => Ok(types::chars::explore(&words.join(" ")).to_string()),
+    _ => Err(String::from(USAGE)),
+  }
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  match run(&args) {
+    Ok(output) => println!("{}", output),
+    Err(message) => {
+      eprintln!("{}", message);
+      process::exit(1);
+    },
+  }
+}
@@ -0,0 +1,391 @@
+// ======================== CHAR EXPLORER ========================
+// variables.rs stores '😂' in a `char`. A char is a Unicode scalar value: a number between
+// U+0000 and U+10FFFF (without the surrogates). It always takes 4 bytes in memory, but when
+// it lives inside a String it is encoded as UTF-8 and takes between 1 and 4 bytes.
+//
+// What a person calls "one character" is yet another thing: a grapheme cluster. "é" can be
+// one char or 'e' followed by a combining accent, and 👍🏽 is two chars. So for a string:
+//   .len()            counts UTF-8 bytes
+//   .chars().count()  counts scalar values
+//   graphemes(..)     counts what a reader sees
+//
+// The standard library has no grapheme or general category tables, so this module carries
+// small ones. They cover the common scripts and emoji, not the whole Unicode database.
+
+use std::fmt;
+
+// One scalar value of the input and where it sits in the string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalarInfo {
+  pub byte_offset: usize,
+  pub character: char,
+  pub utf8: Vec<u8>,
+  pub utf16: Vec<u16>,
+  pub category: GeneralCategory,
+}
+
+impl ScalarInfo {
+  pub fn new(byte_offset: usize, character: char) -> ScalarInfo {
+    let mut utf8 = [0; 4];
+    let mut utf16 = [0; 2];
+    ScalarInfo {
+      byte_offset,
+      character,
+      utf8: character.encode_utf8(&mut utf8).as_bytes().to_vec(),
+      utf16: character.encode_utf16(&mut utf16).to_vec(),
+      category: GeneralCategory::of(character),
+    }
+  }
+
+  pub fn code_point(&self) -> u32 {
+    self.character as u32
+  }
+}
+
+// The Unicode general categories, grouped the way the standard names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneralCategory {
+  UppercaseLetter,
+  LowercaseLetter,
+  OtherLetter,
+  NonspacingMark,
+  SpacingMark,
+  DecimalNumber,
+  OtherNumber,
+  ConnectorPunctuation,
+  DashPunctuation,
+  OpenPunctuation,
+  ClosePunctuation,
+  OtherPunctuation,
+  MathSymbol,
+  CurrencySymbol,
+  ModifierSymbol,
+  OtherSymbol,
+  SpaceSeparator,
+  LineSeparator,
+  ParagraphSeparator,
+  Control,
+  Format,
+  PrivateUse,
+  Unassigned,
+}
+
+impl GeneralCategory {
+  pub fn of(c: char) -> GeneralCategory {
+    let code = c as u32;
+    if c.is_control() {
+      return GeneralCategory::Control;
+    }
+    if c.is_ascii() {
+      return ascii_category(c);
+    }
+    match code {
+      0x00A0 | 0x1680 | 0x2000..=0x200A | 0x202F | 0x205F | 0x3000 => GeneralCategory::SpaceSeparator,
+      0x2028 => GeneralCategory::LineSeparator,
+      0x2029 => GeneralCategory::ParagraphSeparator,
+      0x00AD | 0x0600..=0x0605 | 0x06DD | 0x070F | 0x0890 | 0x0891 | 0x08E2 | 0x200B..=0x200F | 0x202A..=0x202E
+      | 0x2060..=0x2064 | 0xFEFF | 0x110BD | 0x110CD | 0xE0001 | 0xE0020..=0xE007F => GeneralCategory::Format,
+      0xE000..=0xF8FF | 0xF0000..=0xFFFFD | 0x100000..=0x10FFFD => GeneralCategory::PrivateUse,
+      0x2010..=0x2015 | 0x2E3A | 0x2E3B | 0x301C | 0xFE58 | 0xFF0D => GeneralCategory::DashPunctuation,
+      0x00AB | 0x2018 | 0x201C | 0x2039 | 0x3008 | 0x300A | 0x300C | 0x300E | 0x3010 => {
+        GeneralCategory::OpenPunctuation
+      },
+      0x00BB | 0x2019 | 0x201D | 0x203A | 0x3009 | 0x300B | 0x300D | 0x300F | 0x3011 => {
+        GeneralCategory::ClosePunctuation
+      },
+      0x00A1 | 0x00A7 | 0x00B6 | 0x00B7 | 0x00BF | 0x2016 | 0x2017 | 0x2020..=0x2027 | 0x2030..=0x2038
+      | 0x203B..=0x203E | 0x3001..=0x3003 => GeneralCategory::OtherPunctuation,
+      0x203F | 0x2040 | 0x2054 | 0xFE33 | 0xFE34 | 0xFF3F => GeneralCategory::ConnectorPunctuation,
+      0x00A2..=0x00A5 | 0x20A0..=0x20C0 => GeneralCategory::CurrencySymbol,
+      0x00AC | 0x00B1 | 0x00D7 | 0x00F7 | 0x2190..=0x2194 | 0x2200..=0x22FF | 0x27C0..=0x27C4 => {
+        GeneralCategory::MathSymbol
+      },
+      0x00A8 | 0x00AF | 0x00B4 | 0x00B8 | 0x02C2..=0x02C5 | 0x02D2..=0x02DF | 0x1F3FB..=0x1F3FF => {
+        GeneralCategory::ModifierSymbol
+      },
+      0x00B2 | 0x00B3 | 0x00B9 | 0x00BC..=0x00BE | 0x2070..=0x2079 | 0x2080..=0x2089 | 0x2150..=0x215F
+      | 0x2460..=0x249B => GeneralCategory::OtherNumber,
+      _ if is_extend(c) => GeneralCategory::NonspacingMark,
+      _ if is_spacing_mark(c) => GeneralCategory::SpacingMark,
+      _ if c.is_uppercase() => GeneralCategory::UppercaseLetter,
+      _ if c.is_lowercase() => GeneralCategory::LowercaseLetter,
+      _ if c.is_alphabetic() => GeneralCategory::OtherLetter,
+      _ if c.is_numeric() => GeneralCategory::DecimalNumber,
+      _ if is_extended_pictographic(c) || (0x2195..=0x2BFF).contains(&code) || (0x1F1E6..=0x1F1FF).contains(&code) => {
+        GeneralCategory::OtherSymbol
+      },
+      _ => GeneralCategory::Unassigned,
+    }
+  }
+
+  // The two letter abbreviation used in the Unicode tables
+  pub fn code(&self) -> &'static str {
+    match self {
+      GeneralCategory::UppercaseLetter => "Lu",
+      GeneralCategory::LowercaseLetter => "Ll",
+      GeneralCategory::OtherLetter => "Lo",
+      GeneralCategory::NonspacingMark => "Mn",
+      GeneralCategory::SpacingMark => "Mc",
+      GeneralCategory::DecimalNumber => "Nd",
+      GeneralCategory::OtherNumber => "No",
+      GeneralCategory::ConnectorPunctuation => "Pc",
+      GeneralCategory::DashPunctuation => "Pd",
+      GeneralCategory::OpenPunctuation => "Ps",
+      GeneralCategory::ClosePunctuation => "Pe",
+      GeneralCategory::OtherPunctuation => "Po",
+      GeneralCategory::MathSymbol => "Sm",
+      GeneralCategory::CurrencySymbol => "Sc",
+      GeneralCategory::ModifierSymbol => "Sk",
+      GeneralCategory::OtherSymbol => "So",
+      GeneralCategory::SpaceSeparator => "Zs",
+      GeneralCategory::LineSeparator => "Zl",
+      GeneralCategory::ParagraphSeparator => "Zp",
+      GeneralCategory::Control => "Cc",
+      GeneralCategory::Format => "Cf",
+      GeneralCategory::PrivateUse => "Co",
+      GeneralCategory::Unassigned => "Cn",
+    }
+  }
+}
+
+impl fmt::Display for GeneralCategory {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    // UppercaseLetter -> "Uppercase Letter"
+    let name = format!("{:?}", self);
+    let mut spaced = String::new();
+    for (index, c) in name.chars().enumerate() {
+      if index > 0 && c.is_uppercase() {
+        spaced.push(' ');
+      }
+      spaced.push(c);
+    }
+    write!(f, "{} ({})", self.code(), spaced)
+  }
+}
+
+fn ascii_category(c: char) -> GeneralCategory {
+  match c {
+    'A'..='Z' => GeneralCategory::UppercaseLetter,
+    'a'..='z' => GeneralCategory::LowercaseLetter,
+    '0'..='9' => GeneralCategory::DecimalNumber,
+    ' ' => GeneralCategory::SpaceSeparator,
+    '_' => GeneralCategory::ConnectorPunctuation,
+    '-' => GeneralCategory::DashPunctuation,
+    '(' | '[' | '{' => GeneralCategory::OpenPunctuation,
+    ')' | ']' | '}' => GeneralCategory::ClosePunctuation,
+    '+' | '<' | '=' | '>' | '|' | '~' => GeneralCategory::MathSymbol,
+    '$' => GeneralCategory::CurrencySymbol,
+    '^' | '`' => GeneralCategory::ModifierSymbol,
+    _ => GeneralCategory::OtherPunctuation,
+  }
+}
+
+// Marks that attach to the previous character (accents, variation selectors, skin tones...)
+fn is_extend(c: char) -> bool {
+  matches!(c as u32,
+    0x0300..=0x036F | 0x0483..=0x0489 | 0x0591..=0x05BD | 0x05BF | 0x05C1 | 0x05C2 | 0x05C4 | 0x05C5
+    | 0x05C7 | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06DC | 0x06DF..=0x06E4
+    | 0x06E7 | 0x06E8 | 0x06EA..=0x06ED | 0x0900..=0x0902 | 0x093A | 0x093C | 0x0941..=0x0948
+    | 0x094D | 0x0951..=0x0957 | 0x0962 | 0x0963 | 0x0981 | 0x09BC | 0x09C1..=0x09C4 | 0x09CD
+    | 0x09E2 | 0x09E3 | 0x09FE | 0x0A01 | 0x0A02 | 0x0A3C | 0x0A41 | 0x0A42 | 0x0A47 | 0x0A48
+    | 0x0A4B..=0x0A4D | 0x0A51 | 0x0A70 | 0x0A71 | 0x0A75 | 0x0A81 | 0x0A82 | 0x0ABC | 0x0AC1..=0x0AC5
+    | 0x0AC7 | 0x0AC8 | 0x0ACD | 0x0AE2 | 0x0AE3 | 0x0AFA..=0x0AFF | 0x0B01 | 0x0B3C | 0x0B3F
+    | 0x0B41..=0x0B44 | 0x0B4D | 0x0B55 | 0x0B56 | 0x0B62 | 0x0B63 | 0x0B82 | 0x0BC0 | 0x0BCD | 0x0C00
+    | 0x0C04 | 0x0C3C | 0x0C3E..=0x0C40 | 0x0C46..=0x0C48 | 0x0C4A..=0x0C4D | 0x0C55 | 0x0C56 | 0x0C62
+    | 0x0C63 | 0x0C81 | 0x0CBC | 0x0CBF | 0x0CC6 | 0x0CCC | 0x0CCD | 0x0CE2 | 0x0CE3 | 0x0D00 | 0x0D01
+    | 0x0D3B | 0x0D3C | 0x0D41..=0x0D44 | 0x0D4D | 0x0D62 | 0x0D63 | 0x0D81 | 0x0DCA | 0x0DD2..=0x0DD4
+    | 0x0DD6 | 0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E | 0x1AB0..=0x1AFF
+    | 0x1DC0..=0x1DFF | 0x200C | 0x20D0..=0x20FF | 0x302A..=0x302F | 0x3099 | 0x309A
+    | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F | 0xE0100..=0xE01EF)
+}
+
+// Vowel signs written beside the consonant, like the ि and ी of "हिन्दी". They take up room of
+// their own but still belong to the letter before them. Devanagari to Sinhala only.
+fn is_spacing_mark(c: char) -> bool {
+  matches!(c as u32,
+    0x0903 | 0x093B | 0x093E..=0x0940 | 0x0949..=0x094C | 0x094E | 0x094F | 0x0982 | 0x0983
+    | 0x09BE..=0x09C0 | 0x09C7 | 0x09C8 | 0x09CB | 0x09CC | 0x09D7 | 0x0A03 | 0x0A3E..=0x0A40 | 0x0A83
+    | 0x0ABE..=0x0AC0 | 0x0AC9 | 0x0ACB | 0x0ACC | 0x0B02 | 0x0B03 | 0x0B3E | 0x0B40 | 0x0B47 | 0x0B48
+    | 0x0B4B | 0x0B4C | 0x0B57 | 0x0BBE | 0x0BBF | 0x0BC1 | 0x0BC2 | 0x0BC6..=0x0BC8 | 0x0BCA..=0x0BCC
+    | 0x0BD7 | 0x0C01..=0x0C03 | 0x0C41..=0x0C44 | 0x0C82 | 0x0C83 | 0x0CBE | 0x0CC0..=0x0CC4 | 0x0CC7
+    | 0x0CC8 | 0x0CCA | 0x0CCB | 0x0CD5 | 0x0CD6 | 0x0D02 | 0x0D03 | 0x0D3E..=0x0D40 | 0x0D46..=0x0D48
+    | 0x0D4A..=0x0D4C | 0x0D57 | 0x0D82 | 0x0D83 | 0x0DCF..=0x0DD1 | 0x0DD8..=0x0DDF | 0x0DF2 | 0x0DF3)
+}
+
+// Signs written before the text they belong to, like the Arabic number sign U+0600
+fn is_prepend(c: char) -> bool {
+  matches!(c as u32,
+    0x0600..=0x0605 | 0x06DD | 0x070F | 0x0890 | 0x0891 | 0x08E2 | 0x0D4E | 0x110BD | 0x110CD
+    | 0x111C2 | 0x111C3 | 0x1193F | 0x11941 | 0x11A3A | 0x11A84..=0x11A89 | 0x11D46)
+}
+
+// The viramas of Devanagari, Bengali, Gujarati, Oriya, Telugu and Malayalam, which join the
+// consonants around them into one conjunct: "न्द" is a single grapheme
+fn is_linker(c: char) -> bool {
+  matches!(c as u32, 0x094D | 0x09CD | 0x0ACD | 0x0B4D | 0x0C4D | 0x0D4D)
+}
+
+// Emoji and other pictographs that can be glued together with a zero width joiner
+fn is_extended_pictographic(c: char) -> bool {
+  matches!(c as u32,
+    0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x2194..=0x2199 | 0x21A9 | 0x21AA
+    | 0x231A | 0x231B | 0x2328 | 0x23CF | 0x23E9..=0x23F3 | 0x23F8..=0x23FA | 0x24C2 | 0x25AA
+    | 0x25AB | 0x25B6 | 0x25C0 | 0x25FB..=0x25FE | 0x2600..=0x27BF | 0x2934 | 0x2935
+    | 0x2B05..=0x2B07 | 0x2B1B | 0x2B1C | 0x2B50 | 0x2B55 | 0x3030 | 0x303D | 0x3297 | 0x3299
+    | 0x1F000..=0x1F0FF | 0x1F10D..=0x1F10F | 0x1F12F | 0x1F16C..=0x1F171 | 0x1F17E | 0x1F17F
+    | 0x1F18E | 0x1F191..=0x1F19A | 0x1F1AD..=0x1F1E5 | 0x1F201..=0x1F20F | 0x1F21A | 0x1F22F
+    | 0x1F232..=0x1F23A | 0x1F23C..=0x1F23F | 0x1F249..=0x1F3FA | 0x1F400..=0x1F53D
+    | 0x1F546..=0x1F64F | 0x1F680..=0x1F6FF | 0x1F774..=0x1F77F | 0x1F7D5..=0x1F7FF
+    | 0x1F80C..=0x1F80F | 0x1F848..=0x1F84F | 0x1F85A..=0x1F85F | 0x1F888..=0x1F88F
+    | 0x1F8AE..=0x1F8FF | 0x1F90C..=0x1F93A | 0x1F93C..=0x1F945 | 0x1F947..=0x1FAFF
+    | 0x1FC00..=0x1FFFD)
+}
+
+// The grapheme break property from Unicode Standard Annex #29, limited to what we need
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakClass {
+  Cr, Lf, Control, Extend, Zwj, SpacingMark, Prepend, RegionalIndicator, L, V, T, Lv, Lvt, Pictographic, Other,
+}
+
+fn break_class(c: char) -> BreakClass {
+  let code = c as u32;
+  match code {
+    0x000D => BreakClass::Cr,
+    0x000A => BreakClass::Lf,
+    0x200D => BreakClass::Zwj,
+    0x1F1E6..=0x1F1FF => BreakClass::RegionalIndicator,
+    0x1100..=0x115F | 0xA960..=0xA97C => BreakClass::L,
+    0x1160..=0x11A7 | 0xD7B0..=0xD7C6 => BreakClass::V,
+    0x11A8..=0x11FF | 0xD7CB..=0xD7FB => BreakClass::T,
+    // Every 28th precomposed Hangul syllable has no final consonant
+    0xAC00..=0xD7A3 if (code - 0xAC00).is_multiple_of(28) => BreakClass::Lv,
+    0xAC00..=0xD7A3 => BreakClass::Lvt,
+    _ if is_extend(c) => BreakClass::Extend,
+    // Thai and Lao SARA AM are letters, but the standard treats them as spacing marks
+    0x0E33 | 0x0EB3 => BreakClass::SpacingMark,
+    _ if is_spacing_mark(c) => BreakClass::SpacingMark,
+    _ if is_prepend(c) => BreakClass::Prepend,
+    _ if c.is_control() || matches!(code, 0x2028 | 0x2029 | 0x200B | 0x200E | 0x200F | 0xFEFF) => BreakClass::Control,
+    _ if is_extended_pictographic(c) => BreakClass::Pictographic,
+    _ => BreakClass::Other,
+  }
+}
+
+// Byte offsets where a new grapheme cluster starts, plus text.len() at the end
+pub fn grapheme_boundaries(text: &str) -> Vec<usize> {
+  let mut boundaries = vec![0];
+  let mut previous: Option<BreakClass> = None;
+  // Emoji ZWJ sequences: a pictograph followed by extends and a ZWJ glues to the next pictograph
+  let mut in_pictographic_sequence = false;
+  let mut zwj_after_pictographic = false;
+  // Flags are pairs of regional indicators, so we count how many we have seen in a row
+  let mut regional_indicators = 0;
+  // Conjuncts: a consonant, then a virama (maybe among other marks) glues to the next consonant
+  let mut in_consonant_sequence = false;
+  let mut linker_after_consonant = false;
+
+  for (offset, c) in text.char_indices() {
+    let current = break_class(c);
+    if let Some(before) = previous {
+      let keep_together = match (before, current) {
+        (BreakClass::Cr, BreakClass::Lf) => true,
+        (BreakClass::Cr | BreakClass::Lf | BreakClass::Control, _) => false,
+        (_, BreakClass::Cr | BreakClass::Lf | BreakClass::Control) => false,
+        (BreakClass::L, BreakClass::L | BreakClass::V | BreakClass::Lv | BreakClass::Lvt) => true,
+        (BreakClass::Lv | BreakClass::V, BreakClass::V | BreakClass::T) => true,
+        (BreakClass::Lvt | BreakClass::T, BreakClass::T) => true,
+        (_, BreakClass::Extend | BreakClass::Zwj | BreakClass::SpacingMark) => true,
+        (BreakClass::Prepend, _) => true,
+        (_, BreakClass::Other) if c.is_alphabetic() => linker_after_consonant,
+        (BreakClass::Zwj, BreakClass::Pictographic) => zwj_after_pictographic,
+        (BreakClass::RegionalIndicator, BreakClass::RegionalIndicator) => regional_indicators % 2 == 1,
+        _ => false,
+      };
+      if !keep_together {
+        boundaries.push(offset);
+      }
+    }
+
+    zwj_after_pictographic = in_pictographic_sequence && current == BreakClass::Zwj;
+    in_pictographic_sequence = match current {
+      BreakClass::Pictographic => true,
+      BreakClass::Extend => in_pictographic_sequence,
+      _ => false,
+    };
+    let consonant = current == BreakClass::Other && c.is_alphabetic();
+    let joiner = matches!(current, BreakClass::Extend | BreakClass::Zwj);
+    linker_after_consonant = in_consonant_sequence && joiner && (linker_after_consonant || is_linker(c));
+    in_consonant_sequence = consonant || (in_consonant_sequence && joiner);
+    regional_indicators = if current == BreakClass::RegionalIndicator { regional_indicators + 1 } else { 0 };
+    previous = Some(current);
+  }
+
+  if !text.is_empty() {
+    boundaries.push(text.len());
+  }
+  boundaries
+}
+
+pub fn graphemes(text: &str) -> Vec<&str> {
+  grapheme_boundaries(text).windows(2).map(|pair| &text[pair[0]..pair[1]]).collect()
+}
+
+#[derive(Debug)]
+pub struct CharReport<'a> {
+  pub text: &'a str,
+  pub scalars: Vec<ScalarInfo>,
+  pub graphemes: Vec<&'a str>,
+}
+
+pub fn explore(text: &str) -> CharReport<'_> {
+  CharReport {
+    text,
+    scalars: text.char_indices().map(|(offset, c)| ScalarInfo::new(offset, c)).collect(),
+    graphemes: graphemes(text),
+  }
+}
+
+// Controls and invisible characters are escaped, marks are drawn on a dotted circle
+fn printable(c: char) -> String {
+  match GeneralCategory::of(c) {
+    GeneralCategory::Control | GeneralCategory::Format => c.escape_unicode().to_string(),
+    GeneralCategory::NonspacingMark | GeneralCategory::SpacingMark => format!("\u{25CC}{}", c),
+    _ => c.to_string(),
+  }
+}
+
+fn hex_units<T: fmt::UpperHex>(units: &[T], width: usize) -> String {
+  let hex: Vec<String> = units.iter().map(|unit| format!("{:0width$X}", unit, width = width)).collect();
+  hex.join(" ")
+}
+
+impl fmt::Display for CharReport<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "Text: {:?}", self.text)?;
+    writeln!(f)?;
+    writeln!(f, "{:>5}  {:<4} {:<9} {:<12} {:<10} {:<5} Category", "Byte", "Char", "Code", "UTF-8", "UTF-16", "Bytes")?;
+    for scalar in &self.scalars {
+      writeln!(f, "{:>5}  {:<4} U+{:<7X} {:<12} {:<10} {:<5} {}",
+        scalar.byte_offset,
+        printable(scalar.character),
+        scalar.code_point(),
+        hex_units(&scalar.utf8, 2),
+        hex_units(&scalar.utf16, 4),
+        scalar.utf8.len(),
+        scalar.category)?;
+    }
+    writeln!(f)?;
+    writeln!(f, "Grapheme clusters:")?;
+    let mut offset = 0;
+    for grapheme in &self.graphemes {
+      let chars: Vec<String> = grapheme.chars().map(|c| format!("U+{:04X}", c as u32)).collect();
+      writeln!(f, "  {:>5}..{:<5} {}  [{}]", offset, offset + grapheme.len(), grapheme.escape_debug(), chars.join(" "))?;
+      offset += grapheme.len();
+    }
+    writeln!(f)?;
+    writeln!(f, ".len()                 = {:>3}  UTF-8 bytes", self.text.len())?;
+    writeln!(f, ".encode_utf16().count() = {:>2}  UTF-16 code units", self.text.encode_utf16().count())?;
+    writeln!(f, ".chars().count()       = {:>3}  Unicode scalar values", self.scalars.len())?;
+    write!(f, "graphemes(..).len()    = {:>3}  what a reader sees as characters", self.graphemes.len())
+  }
+}
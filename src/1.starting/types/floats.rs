@@ -0,0 +1,340 @@
+// ======================== FLOAT EXPLORER ========================
+// variables.rs declares `let y : f32 = 3.0;` but a float is not stored as "3.0".
+// f32 and f64 follow IEEE-754: one sign bit, some exponent bits and some mantissa bits.
+//
+//   f32:  1 sign | 8 exponent  (bias 127)  | 23 mantissa
+//   f64:  1 sign | 11 exponent (bias 1023) | 52 mantissa
+//
+// This module pulls those pieces apart, tells us which kind of value we have, prints the
+// exact decimal number that the bits store and measures distances in ULPs
+// (units in the last place: how many representable floats lie between two values).
+
+use std::fmt;
+
+// Bit layout of one float type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatFormat {
+  pub name: &'static str,
+  pub exponent_bits: u32,
+  pub mantissa_bits: u32,
+}
+
+impl FloatFormat {
+  pub const F32: FloatFormat = FloatFormat { name: "f32", exponent_bits: 8, mantissa_bits: 23 };
+  pub const F64: FloatFormat = FloatFormat { name: "f64", exponent_bits: 11, mantissa_bits: 52 };
+
+  pub fn total_bits(&self) -> u32 {
+    1 + self.exponent_bits + self.mantissa_bits
+  }
+
+  pub fn bias(&self) -> i32 {
+    (1 << (self.exponent_bits - 1)) - 1
+  }
+
+  fn exponent_mask(&self) -> u64 {
+    (1 << self.exponent_bits) - 1
+  }
+
+  fn mantissa_mask(&self) -> u64 {
+    (1 << self.mantissa_bits) - 1
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatValue { F32(f32), F64(f64) }
+
+// The three fields of the bit pattern, as raw unsigned numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatParts {
+  pub negative: bool,
+  pub exponent: u64,
+  pub mantissa: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatClass {
+  Normal,
+  Subnormal,
+  Zero,
+  Infinite,
+  // The highest mantissa bit tells quiet NaNs from signalling ones, the rest is a payload
+  NaN { quiet: bool, payload: u64 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FloatError {
+  UnknownType(String),
+  InvalidValue { type_name: &'static str, input: String },
+  MismatchedTypes,
+  NaNDistance,
+}
+
+impl fmt::Display for FloatError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FloatError::UnknownType(name) => write!(f, "unknown float type '{}', expected f32 or f64", name),
+      FloatError::InvalidValue { type_name, input } => {
+        write!(f, "'{}' is not a valid {} (use a decimal, inf, NaN or raw bits like 0x40400000)", input, type_name)
+      },
+      FloatError::MismatchedTypes => write!(f, "cannot measure the distance between an f32 and an f64"),
+      FloatError::NaNDistance => write!(f, "NaN is not ordered, so it has no ULP distance"),
+    }
+  }
+}
+
+impl std::error::Error for FloatError {}
+
+impl FloatValue {
+  // Accepts anything `str::parse` understands ("3.0", "-0", "inf", "NaN", "1e-40") and also
+  // raw bit patterns written in hex, which is the only way to build a NaN with a payload
+  pub fn parse(type_name: &str, input: &str) -> Result<FloatValue, FloatError> {
+    let trimmed = input.trim();
+    let hex_bits = trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X"));
+    let parsed = match (type_name, hex_bits) {
+      ("f32", Some(hex)) => u32::from_str_radix(&hex.replace('_', ""), 16).ok().map(|bits| FloatValue::F32(f32::from_bits(bits))),
+      ("f64", Some(hex)) => u64::from_str_radix(&hex.replace('_', ""), 16).ok().map(|bits| FloatValue::F64(f64::from_bits(bits))),
+      ("f32", None) => trimmed.parse::<f32>().ok().map(FloatValue::F32),
+      ("f64", None) => trimmed.parse::<f64>().ok().map(FloatValue::F64),
+      _ => return Err(FloatError::UnknownType(type_name.to_string())),
+    };
+    let format = if type_name == "f32" { FloatFormat::F32 } else { FloatFormat::F64 };
+    parsed.ok_or(FloatError::InvalidValue { type_name: format.name, input: input.to_string() })
+  }
+
+  pub fn format(&self) -> FloatFormat {
+    match self {
+      FloatValue::F32(_) => FloatFormat::F32,
+      FloatValue::F64(_) => FloatFormat::F64,
+    }
+  }
+
+  // The bit pattern, widened to u64 so both types can share the code below
+  pub fn to_bits(self) -> u64 {
+    match self {
+      FloatValue::F32(value) => value.to_bits() as u64,
+      FloatValue::F64(value) => value.to_bits(),
+    }
+  }
+
+  pub fn parts(&self) -> FloatParts {
+    let format = self.format();
+    let bits = self.to_bits();
+    FloatParts {
+      negative: bits >> (format.total_bits() - 1) == 1,
+      exponent: (bits >> format.mantissa_bits) & format.exponent_mask(),
+      mantissa: bits & format.mantissa_mask(),
+    }
+  }
+
+  pub fn classify(&self) -> FloatClass {
+    let format = self.format();
+    let parts = self.parts();
+    match (parts.exponent, parts.mantissa) {
+      (0, 0) => FloatClass::Zero,
+      (0, _) => FloatClass::Subnormal,
+      (exponent, 0) if exponent == format.exponent_mask() => FloatClass::Infinite,
+      (exponent, mantissa) if exponent == format.exponent_mask() => {
+        let quiet_bit = 1 << (format.mantissa_bits - 1);
+        FloatClass::NaN { quiet: mantissa & quiet_bit != 0, payload: mantissa & !quiet_bit }
+      },
+      _ => FloatClass::Normal,
+    }
+  }
+
+  pub fn next_up(&self) -> FloatValue {
+    match self {
+      FloatValue::F32(value) => FloatValue::F32(value.next_up()),
+      FloatValue::F64(value) => FloatValue::F64(value.next_up()),
+    }
+  }
+
+  pub fn next_down(&self) -> FloatValue {
+    match self {
+      FloatValue::F32(value) => FloatValue::F32(value.next_down()),
+      FloatValue::F64(value) => FloatValue::F64(value.next_down()),
+    }
+  }
+
+  // The significand with its implicit leading bit (1 for normal numbers, 0 for subnormals)
+  // and the power of two it is multiplied by: value = significand * 2^exponent
+  fn integer_significand(&self) -> (u64, i32) {
+    let format = self.format();
+    let parts = self.parts();
+    let shift = format.bias() + format.mantissa_bits as i32;
+    if parts.exponent == 0 {
+      (parts.mantissa, 1 - shift)
+    } else {
+      (parts.mantissa | (1 << format.mantissa_bits), parts.exponent as i32 - shift)
+    }
+  }
+
+  // Every finite float is a fraction with a power of two below it, so its decimal expansion
+  // always ends. This prints all of it, e.g. 0.1f32 is really 0.100000001490116119384765625
+  pub fn exact_decimal(&self) -> String {
+    match self.classify() {
+      FloatClass::NaN { .. } => return String::from("NaN"),
+      FloatClass::Infinite => return String::from(if self.parts().negative { "-inf" } else { "inf" }),
+      _ => (),
+    }
+    let (significand, exponent) = self.integer_significand();
+    let sign = if self.parts().negative { "-" } else { "" };
+    format!("{}{}", sign, exact_decimal(significand, exponent))
+  }
+
+  // Distance to the next float up: how coarse the number line is around this value.
+  // The largest float has no next one up, so it uses the gap down to the float before it.
+  // Infinity and NaN have no neighbours at all.
+  pub fn ulp(&self) -> Option<FloatValue> {
+    match *self {
+      FloatValue::F32(value) if value.is_finite() => {
+        let value = value.abs();
+        let gap = if value == f32::MAX { value - value.next_down() } else { value.next_up() - value };
+        Some(FloatValue::F32(gap))
+      },
+      FloatValue::F64(value) if value.is_finite() => {
+        let value = value.abs();
+        let gap = if value == f64::MAX { value - value.next_down() } else { value.next_up() - value };
+        Some(FloatValue::F64(gap))
+      },
+      _ => None,
+    }
+  }
+
+  // Map the bits to a number line where every next float is exactly one step further.
+  // Positive floats already are ordered by their bits; negative floats count down from -0.
+  fn ordered_index(&self) -> i128 {
+    let parts = self.parts();
+    let magnitude = (self.to_bits() & !(1 << (self.format().total_bits() - 1))) as i128;
+    if parts.negative { -magnitude } else { magnitude }
+  }
+}
+
+impl fmt::Display for FloatValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FloatValue::F32(value) => write!(f, "{:?}", value),
+      FloatValue::F64(value) => write!(f, "{:?}", value),
+    }
+  }
+}
+
+impl fmt::Display for FloatClass {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FloatClass::Normal => write!(f, "normal"),
+      FloatClass::Subnormal => write!(f, "subnormal (no implicit leading 1, precision is lost)"),
+      FloatClass::Zero => write!(f, "zero"),
+      FloatClass::Infinite => write!(f, "infinite"),
+      FloatClass::NaN { quiet, payload } => {
+        write!(f, "NaN ({}, payload 0x{:x})", if *quiet { "quiet" } else { "signalling" }, payload)
+      },
+    }
+  }
+}
+
+// How many representable floats we step over going from `a` to `b`.
+// 0.0 and -0.0 are the same number, so their distance is 0.
+pub fn ulp_distance(a: FloatValue, b: FloatValue) -> Result<u64, FloatError> {
+  if a.format() != b.format() {
+    return Err(FloatError::MismatchedTypes);
+  }
+  if matches!(a.classify(), FloatClass::NaN { .. }) || matches!(b.classify(), FloatClass::NaN { .. }) {
+    return Err(FloatError::NaNDistance);
+  }
+  Ok((a.ordered_index() - b.ordered_index()).unsigned_abs() as u64)
+}
+
+// Decimal digits stored least significant first, so carrying is a push at the end
+fn multiply_digits(digits: &mut Vec<u8>, factor: u8) {
+  let mut carry = 0;
+  for digit in digits.iter_mut() {
+    let product = *digit as u32 * factor as u32 + carry;
+    *digit = (product % 10) as u8;
+    carry = product / 10;
+  }
+  while carry > 0 {
+    digits.push((carry % 10) as u8);
+    carry /= 10;
+  }
+}
+
+// significand * 2^exponent written out in base 10 without rounding.
+// For a negative exponent we use  x / 2^k = x * 5^k / 10^k  and move the decimal point.
+fn exact_decimal(significand: u64, exponent: i32) -> String {
+  let mut digits: Vec<u8> = significand.to_string().bytes().rev().map(|byte| byte - b'0').collect();
+  let factor = if exponent >= 0 { 2 } else { 5 };
+  for _ in 0..exponent.unsigned_abs() {
+    multiply_digits(&mut digits, factor);
+  }
+
+  let decimals = if exponent < 0 { exponent.unsigned_abs() as usize } else { 0 };
+  while digits.len() <= decimals {
+    digits.push(0);
+  }
+  let text: String = digits.iter().rev().map(|digit| (b'0' + digit) as char).collect();
+  let (integer, fraction) = text.split_at(text.len() - decimals);
+  let integer = integer.trim_start_matches('0');
+  let integer = if integer.is_empty() { "0" } else { integer };
+  let fraction = fraction.trim_end_matches('0');
+  if fraction.is_empty() {
+    integer.to_string()
+  } else {
+    format!("{}.{}", integer, fraction)
+  }
+}
+
+#[derive(Debug)]
+pub struct FloatReport {
+  pub value: FloatValue,
+  pub other: Option<FloatValue>,
+}
+
+pub fn explore(type_name: &str, value: &str, other: Option<&str>) -> Result<FloatReport, FloatError> {
+  let value = FloatValue::parse(type_name, value)?;
+  let other = match other {
+    Some(input) => Some(FloatValue::parse(type_name, input)?),
+    None => None,
+  };
+  Ok(FloatReport { value, other })
+}
+
+impl fmt::Display for FloatReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let value = self.value;
+    let format = value.format();
+    let parts = value.parts();
+    let bits = format!("{:0width$b}", value.to_bits(), width = format.total_bits() as usize);
+    let (sign_bit, rest) = bits.split_at(1);
+    let (exponent_bits, mantissa_bits) = rest.split_at(format.exponent_bits as usize);
+
+    writeln!(f, "Type:        {} ({} exponent bits, {} mantissa bits, bias {})",
+      format.name, format.exponent_bits, format.mantissa_bits, format.bias())?;
+    writeln!(f, "Value:       {}", value)?;
+    writeln!(f, "Bits:        {} {} {}", sign_bit, exponent_bits, mantissa_bits)?;
+    writeln!(f, "Hex:         0x{:0width$x}", value.to_bits(), width = format.total_bits() as usize / 4)?;
+    writeln!(f, "Sign:        {} ({})", sign_bit, if parts.negative { "-" } else { "+" })?;
+    match value.classify() {
+      FloatClass::Normal => writeln!(f, "Exponent:    {} - {} = {}",
+        parts.exponent, format.bias(), parts.exponent as i32 - format.bias())?,
+      FloatClass::Subnormal => writeln!(f, "Exponent:    0 (subnormal, acts as {})", 1 - format.bias())?,
+      _ => writeln!(f, "Exponent:    {} (special)", parts.exponent)?,
+    }
+    writeln!(f, "Mantissa:    0x{:x}", parts.mantissa)?;
+    writeln!(f, "Class:       {}", value.classify())?;
+    writeln!(f, "Exact value: {}", value.exact_decimal())?;
+    writeln!(f, "Next up:     {}", value.next_up())?;
+    writeln!(f, "Next down:   {}", value.next_down())?;
+    match value.ulp() {
+      Some(ulp) => write!(f, "ULP size:    {}", ulp)?,
+      None => write!(f, "ULP size:    none, {} is not a finite number", value)?,
+    }
+    if let Some(other) = self.other {
+      writeln!(f)?;
+      match ulp_distance(value, other) {
+        Ok(distance) => write!(f, "ULPs to {}: {}", other, distance)?,
+        Err(error) => write!(f, "ULPs to {}: {}", other, error)?,
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,234 @@
+// ======================== INTEGER EXPLORER ========================
+// variables.rs tells us Rust has integers of 8, 16, 32, 64 and 128 bits, signed (i) and
+// unsigned (u), plus isize and usize which depend on the architecture of the computer.
+// This module lets us look at one of those types with a concrete value: how many bytes it
+// takes, its limits, its bits, and what each flavour of addition does when it overflows.
+
+use std::fmt;
+use std::mem;
+
+// Every integer type the explorer understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntType { I8, I16, I32, I64, I128, Isize, U8, U16, U32, U64, U128, Usize }
+
+impl IntType {
+  pub const ALL: [IntType; 12] = [
+    IntType::I8, IntType::I16, IntType::I32, IntType::I64, IntType::I128, IntType::Isize,
+    IntType::U8, IntType::U16, IntType::U32, IntType::U64, IntType::U128, IntType::Usize,
+  ];
+
+  pub fn from_name(name: &str) -> Option<IntType> {
+    IntType::ALL.iter().copied().find(|int_type| int_type.name() == name)
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      IntType::I8 => "i8",
+      IntType::I16 => "i16",
+      IntType::I32 => "i32",
+      IntType::I64 => "i64",
+      IntType::I128 => "i128",
+      IntType::Isize => "isize",
+      IntType::U8 => "u8",
+      IntType::U16 => "u16",
+      IntType::U32 => "u32",
+      IntType::U64 => "u64",
+      IntType::U128 => "u128",
+      IntType::Usize => "usize",
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IntError {
+  UnknownType(String),
+  InvalidValue { type_name: &'static str, input: String, reason: String },
+}
+
+impl fmt::Display for IntError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IntError::UnknownType(name) => {
+        let known: Vec<&str> = IntType::ALL.iter().map(|int_type| int_type.name()).collect();
+        write!(f, "unknown integer type '{}', expected one of: {}", name, known.join(", "))
+      },
+      IntError::InvalidValue { type_name, input, reason } => {
+        write!(f, "'{}' is not a valid {}: {}", input, type_name, reason)
+      },
+    }
+  }
+}
+
+impl std::error::Error for IntError {}
+
+// Everything the explorer found out about one value, already formatted as text.
+// The numbers have different types for every IntType, so keeping Strings here lets one
+// struct describe all of them.
+#[derive(Debug)]
+pub struct IntReport {
+  pub type_name: &'static str,
+  pub pointer_sized: bool,
+  pub size: usize,
+  pub align: usize,
+  pub min: String,
+  pub max: String,
+  pub value: String,
+  pub binary: String,
+  pub hex: String,
+  // The same bits read as the other signedness (i8 <-> u8, ...)
+  pub reinterpreted_as: &'static str,
+  pub reinterpreted: String,
+  pub swapped: String,
+  pub le_bytes: String,
+  pub be_bytes: String,
+  pub addend: String,
+  pub wrapping: String,
+  pub saturating: String,
+  pub checked: String,
+  pub overflowing: String,
+}
+
+// Parse "42", "-42", "0x2a", "0b101010", "0o52" or "1_000" into the requested type
+macro_rules! parse_int {
+  ($t:ty, $input:expr) => {{
+    let cleaned: String = $input.trim().chars().filter(|c| *c != '_').collect();
+    let (negative, unsigned_part) = match cleaned.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+    };
+    let (radix, digits) = match unsigned_part.get(..2) {
+      Some("0x") | Some("0X") => (16, &unsigned_part[2..]),
+      Some("0b") | Some("0B") => (2, &unsigned_part[2..]),
+      Some("0o") | Some("0O") => (8, &unsigned_part[2..]),
+      _ => (10, unsigned_part),
+    };
+    // The one sign we allow came before the prefix, from_str_radix would take a second one
+    // after it ("0x-5", "+-5")
+    if digits.starts_with(['+', '-']) {
+      Err(IntError::InvalidValue {
+        type_name: stringify!($t),
+        input: $input.to_string(),
+        reason: String::from("the sign goes before the radix prefix, and only once"),
+      })
+    } else {
+      let signed_digits = if negative { format!("-{}", digits) } else { digits.to_string() };
+      <$t>::from_str_radix(&signed_digits, radix).map_err(|error| IntError::InvalidValue {
+        type_name: stringify!($t),
+        input: $input.to_string(),
+        reason: if negative && <$t>::MIN == 0 {
+          String::from("unsigned types cannot hold negative numbers")
+        } else {
+          format!("{} (range is {} to {})", error, <$t>::MIN, <$t>::MAX)
+        },
+      })
+    }
+  }};
+}
+
+// Group a string of digits in chunks from the right, "11110000" -> "1111_0000"
+fn group_digits(digits: &str, chunk: usize) -> String {
+  let mut grouped = String::new();
+  for (index, digit) in digits.chars().enumerate() {
+    if index > 0 && (digits.len() - index).is_multiple_of(chunk) {
+      grouped.push('_');
+    }
+    grouped.push(digit);
+  }
+  grouped
+}
+
+fn format_bytes(bytes: &[u8]) -> String {
+  let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+  format!("[{}]", hex.join(" "))
+}
+
+// Build the report for one concrete type. $other is the type with the same width but the
+// opposite signedness, used to show the two's-complement interpretation of the bits.
+macro_rules! describe_int {
+  ($t:ty, $other:ty, $value:expr, $addend:expr) => {{
+    let value: $t = match $value {
+      Some(input) => parse_int!($t, input)?,
+      None => <$t>::MAX,
+    };
+    let addend: $t = match $addend {
+      Some(input) => parse_int!($t, input)?,
+      None => 1,
+    };
+    let bits = <$t>::BITS as usize;
+    let (overflowed_sum, overflowed) = value.overflowing_add(addend);
+    IntReport {
+      type_name: stringify!($t),
+      pointer_sized: stringify!($t).ends_with("size"),
+      size: mem::size_of::<$t>(),
+      align: mem::align_of::<$t>(),
+      min: <$t>::MIN.to_string(),
+      max: <$t>::MAX.to_string(),
+      value: value.to_string(),
+      binary: group_digits(&format!("{:0width$b}", value, width = bits), 8),
+      hex: format!("0x{}", group_digits(&format!("{:0width$x}", value, width = bits / 4), 4)),
+      reinterpreted_as: stringify!($other),
+      reinterpreted: (value as $other).to_string(),
+      swapped: value.swap_bytes().to_string(),
+      le_bytes: format_bytes(&value.to_le_bytes()),
+      be_bytes: format_bytes(&value.to_be_bytes()),
+      addend: addend.to_string(),
+      wrapping: value.wrapping_add(addend).to_string(),
+      saturating: value.saturating_add(addend).to_string(),
+      checked: match value.checked_add(addend) {
+        Some(sum) => format!("Some({})", sum),
+        None => String::from("None"),
+      },
+      overflowing: format!("({}, {})", overflowed_sum, overflowed),
+    }
+  }};
+}
+
+// Explore `value` (MAX when missing, so the overflow is easy to see) plus `addend` (1 when missing)
+pub fn explore(type_name: &str, value: Option<&str>, addend: Option<&str>) -> Result<IntReport, IntError> {
+  let int_type = match IntType::from_name(type_name) {
+    Some(int_type) => int_type,
+    None => return Err(IntError::UnknownType(type_name.to_string())),
+  };
+
+  let report = match int_type {
+    IntType::I8 => describe_int!(i8, u8, value, addend),
+    IntType::I16 => describe_int!(i16, u16, value, addend),
+    IntType::I32 => describe_int!(i32, u32, value, addend),
+    IntType::I64 => describe_int!(i64, u64, value, addend),
+    IntType::I128 => describe_int!(i128, u128, value, addend),
+    IntType::Isize => describe_int!(isize, usize, value, addend),
+    IntType::U8 => describe_int!(u8, i8, value, addend),
+    IntType::U16 => describe_int!(u16, i16, value, addend),
+    IntType::U32 => describe_int!(u32, i32, value, addend),
+    IntType::U64 => describe_int!(u64, i64, value, addend),
+    IntType::U128 => describe_int!(u128, i128, value, addend),
+    IntType::Usize => describe_int!(usize, isize, value, addend),
+  };
+  Ok(report)
+}
+
+impl fmt::Display for IntReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "Type:          {}", self.type_name)?;
+    if self.pointer_sized {
+      writeln!(f, "               (pointer-sized, {} bits on this computer)", self.size * 8)?;
+    }
+    writeln!(f, "Size:          {} bytes", self.size)?;
+    writeln!(f, "Alignment:     {} bytes", self.align)?;
+    writeln!(f, "Range:         {} ..= {}", self.min, self.max)?;
+    writeln!(f)?;
+    writeln!(f, "Value:         {}", self.value)?;
+    writeln!(f, "Binary:        {}", self.binary)?;
+    writeln!(f, "Hex:           {}", self.hex)?;
+    writeln!(f, "As {:<10}  {}", format!("{}:", self.reinterpreted_as), self.reinterpreted)?;
+    writeln!(f, "Little endian: {}", self.le_bytes)?;
+    writeln!(f, "Big endian:    {}", self.be_bytes)?;
+    writeln!(f, "Bytes swapped: {}", self.swapped)?;
+    writeln!(f)?;
+    writeln!(f, "{} + {}", self.value, self.addend)?;
+    writeln!(f, "  wrapping_add:    {}", self.wrapping)?;
+    writeln!(f, "  saturating_add:  {}", self.saturating)?;
+    writeln!(f, "  checked_add:     {}", self.checked)?;
+    write!(f, "  overflowing_add: {}", self.overflowing)
+  }
+}
@@ -0,0 +1,7 @@
+// The types module groups small "explorers" that look inside Rust's primitive types.
+// Each submodule parses what the learner typed, builds a report and implements Display
+// so the explorer binary only has to print it.
+
+pub mod integers;
+pub mod floats;
+pub mod chars;
@@ -220,6 +220,8 @@ fn main() {
 
   // Redefine the enum variants to use the data from the new structs
   // Update the page Load variant to have the boolean type
+  // This WebEvent stays here on purpose: the lesson is about writing an enum yourself. The
+  // tools use the full model in events/mod.rs, with typed keys, buttons and timestamps.
   enum WebEvent { 
     WELoad(bool), 
     WEClick(MouseClick),
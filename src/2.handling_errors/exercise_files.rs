@@ -1,29 +1,57 @@
-use std::fs::File;
-use std::io::{Error, Read};
-use std::path::PathBuf;
-
-fn read_file_contents(path: PathBuf) -> Result<String, Error> {
-  let mut string = String::new();
+// The reading itself now lives in files/mod.rs, whose FileError says which file failed and why,
+// and files/encoding.rs, which also reads text that isn't UTF-8
+mod files;
 
-  let mut file: File = match File::open(path) {
-    Ok(file_handle) => file_handle,
-    Err(io_error) => return Err(io_error)
-  };
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
 
+use files::encoding::{self, Encoding, LoadOptions};
+use files::FileError;
 
-  match file.read_to_string(&mut string) {
-    Ok(_) => (),
-    Err(io_error) => return Err(io_error)
-  };
+fn read_file_contents(path: PathBuf, options: &LoadOptions) -> Result<String, FileError> {
+  let decoded = encoding::load(&path, options)?;
+  for replaced in &decoded.replaced {
+    eprintln!("{}: replaced {} invalid bytes at offset {}", path.display(), replaced.length, replaced.offset);
+  }
+  Ok(decoded.text)
+}
 
-  return Ok(string);
+// The error and every error underneath it, one per line
+fn report(error: &dyn Error) {
+  eprintln!("error: {}", error);
+  let mut source = error.source();
+  while let Some(cause) = source {
+    eprintln!("  caused by: {}", cause);
+    source = cause.source();
+  }
 }
 
 fn main() {
-  if read_file_contents(PathBuf::from("../src/main.rs")).is_ok() {
+  let options = LoadOptions::new();
+  if read_file_contents(PathBuf::from("../src/main.rs"), &options).is_ok() {
     println!("The program found the main file.");
   }
-  if read_file_contents(PathBuf::from("non-existent-file.txt")).is_err() {
-    println!("The program reported an error for the file that doesn't exist.");
+  match read_file_contents(PathBuf::from("non-existent-file.txt"), &options) {
+    Ok(_) => println!("The file that shouldn't exist was found."),
+    Err(error) => {
+      println!("The program reported an error for the file that doesn't exist.");
+      report(&error);
+    },
+  }
+
+  // "Café – 5€" as an old Windows program would save it, with Windows line endings
+  let path = env::temp_dir().join("exercise-files-1252.txt");
+  if fs::write(&path, b"Caf\xe9 \x96 5\x80\r\n").is_err() {
+    return;
   }
-}
\ No newline at end of file
+  if let Err(error) = read_file_contents(path.clone(), &options) {
+    report(&error);
+  }
+  let windows = options.with_fallback(Encoding::Windows1252).normalising_line_endings();
+  assert_eq!(read_file_contents(path.clone(), &windows).ok().as_deref(), Some("Café – 5€\n"));
+  let lossy = options.lossy();
+  assert_eq!(read_file_contents(path.clone(), &lossy).ok().as_deref(), Some("Caf\u{FFFD} \u{FFFD} 5\u{FFFD}\r\n"));
+  let _ = fs::remove_file(path);
+}
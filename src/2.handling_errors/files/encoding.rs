@@ -0,0 +1,213 @@
+// ======================== TEXT ENCODINGS ========================
+// read_to_string only takes UTF-8, but text files come in other encodings too. load looks at
+// the first bytes for a byte order mark (BOM) that says which encoding a file uses:
+//
+//   > EF BB BF   UTF-8 (written by Notepad and Excel)
+//   > FF FE      UTF-16, little endian
+//   > FE FF      UTF-16, big endian
+//
+// The BOM isn't part of the text and is dropped. Without one the file has to be UTF-8,
+// unless a fallback is given for files that aren't:
+//
+//   > Latin1: every byte is the char with the same number, so it can't fail
+//   > Windows1252: Latin-1 with letters and punctuation in 0x80-0x9F (€ ‘ ’ “ ” Œ š ...),
+//     what old Windows programs wrote. The five bytes it leaves undefined stay as they are
+//     in Latin-1, the way browsers read them.
+//
+// Invalid text is an error, unless the load is lossy: then each invalid sequence becomes
+// U+FFFD (�) and its byte offset in the file is reported, so the caller can say where the
+// file was damaged instead of silently changing it.
+//
+// Text from Windows ends lines with \r\n and old Mac files with \r alone. normalise_line_endings
+// makes every line end with \n.
+
+use std::fmt;
+use std::path::Path;
+
+use super::{read_bytes, Cause, FileError, FileErrorKind, Operation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding { Utf8, Utf16Le, Utf16Be, Latin1, Windows1252 }
+
+impl fmt::Display for Encoding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Encoding::Utf8 => write!(f, "UTF-8"),
+      Encoding::Utf16Le => write!(f, "UTF-16LE"),
+      Encoding::Utf16Be => write!(f, "UTF-16BE"),
+      Encoding::Latin1 => write!(f, "Latin-1"),
+      Encoding::Windows1252 => write!(f, "Windows-1252"),
+    }
+  }
+}
+
+// The BOM's encoding and length
+pub fn detect_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+  match bytes {
+    [0xEF, 0xBB, 0xBF, ..] => Some((Encoding::Utf8, 3)),
+    [0xFF, 0xFE, ..] => Some((Encoding::Utf16Le, 2)),
+    [0xFE, 0xFF, ..] => Some((Encoding::Utf16Be, 2)),
+    _ => None,
+  }
+}
+
+// Windows-1252 bytes 0x80 to 0x9F; the \u{81}-style ones are undefined and kept as in Latin-1
+const WINDOWS_1252: [char; 32] = [
+  '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+  '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+// An invalid sequence that a lossy load replaced with U+FFFD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replacement {
+  // Where the sequence starts in the file, BOM included
+  pub offset: usize,
+  pub length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+  pub text: String,
+  pub encoding: Encoding,
+  pub bom: bool,
+  pub replaced: Vec<Replacement>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LoadOptions {
+  // The encoding of files without a BOM that aren't valid UTF-8
+  pub fallback: Option<Encoding>,
+  pub lossy: bool,
+  pub normalise_line_endings: bool,
+}
+
+impl LoadOptions {
+  pub fn new() -> LoadOptions {
+    LoadOptions::default()
+  }
+
+  pub fn with_fallback(mut self, encoding: Encoding) -> LoadOptions {
+    self.fallback = Some(encoding);
+    self
+  }
+
+  pub fn lossy(mut self) -> LoadOptions {
+    self.lossy = true;
+    self
+  }
+
+  pub fn normalising_line_endings(mut self) -> LoadOptions {
+    self.normalise_line_endings = true;
+    self
+  }
+}
+
+// Every \r\n and lone \r becomes \n
+pub fn normalise_line_endings(text: &str) -> String {
+  text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+// Why bytes couldn't be decoded: where, and the error underneath if there is one
+type Invalid = (usize, Cause);
+
+fn decode_utf8(bytes: &[u8], start: usize, lossy: bool, replaced: &mut Vec<Replacement>) -> Result<String, Invalid> {
+  if !lossy {
+    let offset = |error: &std::string::FromUtf8Error| start + error.utf8_error().valid_up_to();
+    return String::from_utf8(bytes.to_vec()).map_err(|error| (offset(&error), Cause::Utf8(error)));
+  }
+  let mut text = String::new();
+  let mut rest = bytes;
+  let mut offset = start;
+  loop {
+    match std::str::from_utf8(rest) {
+      Ok(valid) => {
+        text.push_str(valid);
+        return Ok(text);
+      },
+      Err(error) => {
+        let valid = error.valid_up_to();
+        // Checked to be valid just now, so this never fails
+        text.push_str(std::str::from_utf8(&rest[..valid]).unwrap_or_default());
+        // No error_len means the text stops in the middle of a char
+        let length = error.error_len().unwrap_or(rest.len() - valid);
+        replaced.push(Replacement { offset: offset + valid, length });
+        text.push('\u{FFFD}');
+        offset += valid + length;
+        rest = &rest[valid + length..];
+      },
+    }
+  }
+}
+
+fn decode_utf16(bytes: &[u8], start: usize, big_endian: bool, lossy: bool, replaced: &mut Vec<Replacement>)
+  -> Result<String, Invalid> {
+  let units = bytes.chunks_exact(2).map(|pair| {
+    let pair = [pair[0], pair[1]];
+    if big_endian { u16::from_be_bytes(pair) } else { u16::from_le_bytes(pair) }
+  });
+  let mut text = String::new();
+  // Counted in units, so the offset of a unit is start + 2 * index
+  let mut index = 0;
+  for decoded in char::decode_utf16(units) {
+    match decoded {
+      Ok(c) => {
+        text.push(c);
+        index += c.len_utf16();
+      },
+      Err(_) if lossy => {
+        replaced.push(Replacement { offset: start + 2 * index, length: 2 });
+        text.push('\u{FFFD}');
+        index += 1;
+      },
+      Err(error) => return Err((start + 2 * index, Cause::Utf16(error))),
+    }
+  }
+  // A last byte on its own is half a unit
+  if bytes.len() % 2 == 1 {
+    let offset = start + bytes.len() - 1;
+    if !lossy {
+      return Err((offset, Cause::None));
+    }
+    replaced.push(Replacement { offset, length: 1 });
+    text.push('\u{FFFD}');
+  }
+  Ok(text)
+}
+
+fn decode_single_byte(bytes: &[u8], encoding: Encoding) -> String {
+  bytes.iter().map(|&byte| match (encoding, byte) {
+    (Encoding::Windows1252, 0x80..=0x9F) => WINDOWS_1252[usize::from(byte - 0x80)],
+    _ => char::from(byte),
+  }).collect()
+}
+
+// Decodes bytes read from `path`, which is only used in errors
+pub fn decode(path: &Path, bytes: &[u8], options: &LoadOptions) -> Result<Decoded, FileError> {
+  let (encoding, bom) = match (detect_bom(bytes), options.fallback) {
+    (Some(found), _) => found,
+    (None, Some(fallback)) if std::str::from_utf8(bytes).is_err() => (fallback, 0),
+    _ => (Encoding::Utf8, 0),
+  };
+  let body = &bytes[bom..];
+  let mut replaced = Vec::new();
+  let decoded = match encoding {
+    Encoding::Utf8 => decode_utf8(body, bom, options.lossy, &mut replaced),
+    Encoding::Utf16Le => decode_utf16(body, bom, false, options.lossy, &mut replaced),
+    Encoding::Utf16Be => decode_utf16(body, bom, true, options.lossy, &mut replaced),
+    Encoding::Latin1 | Encoding::Windows1252 => Ok(decode_single_byte(body, encoding)),
+  };
+  let text = decoded.map_err(|(offset, cause)| {
+    let kind = match encoding {
+      Encoding::Utf16Le | Encoding::Utf16Be => FileErrorKind::InvalidUtf16 { offset },
+      _ => FileErrorKind::InvalidUtf8 { valid_up_to: offset },
+    };
+    FileError { path: path.to_path_buf(), operation: Operation::Decode, kind, cause }
+  })?;
+  let text = if options.normalise_line_endings { normalise_line_endings(&text) } else { text };
+  Ok(Decoded { text, encoding, bom: bom > 0, replaced })
+}
+
+pub fn load(path: impl AsRef<Path>, options: &LoadOptions) -> Result<Decoded, FileError> {
+  let path = path.as_ref();
+  decode(path, &read_bytes(path)?, options)
+}
@@ -0,0 +1,174 @@
+// ======================== READING FILES ========================
+// exercise_files.rs first passed io::Error straight through, so a missing file was reported
+// as "No such file or directory (os error 2)": which file, and what were we doing with it?
+// A FileError keeps that context next to the error itself:
+//
+//   > the path of the file
+//   > the operation that failed: opening it, reading it, or decoding its bytes as text
+//   > the io::ErrorKind underneath, and the original error as its source()
+//
+// The failures people can do something about get their own FileErrorKind: the file doesn't
+// exist, we aren't allowed to read it, it is a directory, or it isn't UTF-8 text (with the
+// byte where the text stops being valid). Everything else keeps its io::ErrorKind.
+//
+// read_to_string only reads UTF-8; encoding.rs loads text in other encodings too.
+//
+// Several binaries include this module and each one uses a different part of it.
+#![allow(dead_code)]
+
+pub mod encoding;
+
+use std::char::DecodeUtf16Error;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::string::FromUtf8Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation { Open, Read, Decode }
+
+impl fmt::Display for Operation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Operation::Open => write!(f, "open"),
+      Operation::Read => write!(f, "read"),
+      Operation::Decode => write!(f, "decode"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileErrorKind {
+  NotFound,
+  PermissionDenied,
+  IsADirectory,
+  // The bytes up to `valid_up_to` are fine, the one there starts an invalid sequence
+  InvalidUtf8 { valid_up_to: usize },
+  // A UTF-16 unit that is half of a pair without its other half, or a lone last byte
+  InvalidUtf16 { offset: usize },
+  Other(io::ErrorKind),
+}
+
+// What went wrong underneath, kept so source() can hand it out
+#[derive(Debug)]
+enum Cause {
+  Io(io::Error),
+  Utf8(FromUtf8Error),
+  Utf16(DecodeUtf16Error),
+  // Found out by looking at the file, there is no error to pass on
+  None,
+}
+
+#[derive(Debug)]
+pub struct FileError {
+  path: PathBuf,
+  operation: Operation,
+  kind: FileErrorKind,
+  cause: Cause,
+}
+
+impl FileError {
+  fn io(path: &Path, operation: Operation, error: io::Error) -> FileError {
+    let kind = match error.kind() {
+      io::ErrorKind::NotFound => FileErrorKind::NotFound,
+      io::ErrorKind::IsADirectory => FileErrorKind::IsADirectory,
+      // Windows won't open directories at all and says access is denied
+      io::ErrorKind::PermissionDenied if path.is_dir() => FileErrorKind::IsADirectory,
+      io::ErrorKind::PermissionDenied => FileErrorKind::PermissionDenied,
+      other => FileErrorKind::Other(other),
+    };
+    FileError { path: path.to_path_buf(), operation, kind, cause: Cause::Io(error) }
+  }
+
+  fn directory(path: &Path) -> FileError {
+    let kind = FileErrorKind::IsADirectory;
+    FileError { path: path.to_path_buf(), operation: Operation::Open, kind, cause: Cause::None }
+  }
+
+  fn utf8(path: &Path, error: FromUtf8Error) -> FileError {
+    let kind = FileErrorKind::InvalidUtf8 { valid_up_to: error.utf8_error().valid_up_to() };
+    FileError { path: path.to_path_buf(), operation: Operation::Decode, kind, cause: Cause::Utf8(error) }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn operation(&self) -> Operation {
+    self.operation
+  }
+
+  pub fn kind(&self) -> FileErrorKind {
+    self.kind
+  }
+
+  // The io::ErrorKind this error would have had as a plain io::Error
+  pub fn io_kind(&self) -> io::ErrorKind {
+    match self.kind {
+      FileErrorKind::NotFound => io::ErrorKind::NotFound,
+      FileErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+      FileErrorKind::IsADirectory => io::ErrorKind::IsADirectory,
+      FileErrorKind::InvalidUtf8 { .. } | FileErrorKind::InvalidUtf16 { .. } => io::ErrorKind::InvalidData,
+      FileErrorKind::Other(kind) => kind,
+    }
+  }
+}
+
+impl fmt::Display for FileError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "couldn't {} {}: ", self.operation, self.path.display())?;
+    // Only the kind: the io::Error itself is the source(), and a report printing the chain
+    // would print its message twice
+    match self.kind {
+      FileErrorKind::NotFound => write!(f, "the file doesn't exist"),
+      FileErrorKind::PermissionDenied => write!(f, "permission denied"),
+      FileErrorKind::IsADirectory => write!(f, "it is a directory, not a file"),
+      FileErrorKind::InvalidUtf8 { valid_up_to } => write!(f, "not UTF-8 text, invalid byte at offset {}", valid_up_to),
+      FileErrorKind::InvalidUtf16 { offset } => write!(f, "not UTF-16 text, invalid unit at offset {}", offset),
+      FileErrorKind::Other(kind) => write!(f, "{}", kind),
+    }
+  }
+}
+
+impl Error for FileError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match &self.cause {
+      Cause::Io(error) => Some(error),
+      Cause::Utf8(error) => Some(error),
+      Cause::Utf16(error) => Some(error),
+      Cause::None => None,
+    }
+  }
+}
+
+// This lets `?` turn a FileError into an io::Error with the same kind and the context in
+// its message, for code that still returns io::Result
+impl From<FileError> for io::Error {
+  fn from(error: FileError) -> io::Error {
+    io::Error::new(error.io_kind(), error)
+  }
+}
+
+fn open(path: &Path) -> Result<File, FileError> {
+  let file = File::open(path).map_err(|error| FileError::io(path, Operation::Open, error))?;
+  // On Linux a directory opens fine and only fails when read, so check right away
+  let metadata = file.metadata().map_err(|error| FileError::io(path, Operation::Open, error))?;
+  if metadata.is_dir() {
+    return Err(FileError::directory(path));
+  }
+  Ok(file)
+}
+
+pub fn read_bytes(path: impl AsRef<Path>) -> Result<Vec<u8>, FileError> {
+  let path = path.as_ref();
+  let mut bytes = Vec::new();
+  open(path)?.read_to_end(&mut bytes).map_err(|error| FileError::io(path, Operation::Read, error))?;
+  Ok(bytes)
+}
+
+pub fn read_to_string(path: impl AsRef<Path>) -> Result<String, FileError> {
+  let path = path.as_ref();
+  String::from_utf8(read_bytes(path)?).map_err(|error| FileError::utf8(path, error))
+}
@@ -0,0 +1,74 @@
+// A tour of the person module that grew out of exercise1.rs: parsing names, writing them in
+// other styles, initials, sorting like a phone book and finding duplicates.
+//
+// Build:  rustc names.rs
+// Usage:  ./names
+
+mod person;
+
+use person::collate::{Collator, Locale};
+use person::dedup::Deduplicator;
+use person::format::{format_name, NameStyle};
+use person::initials::{initials, monogram};
+use person::parse::parse_name;
+use person::{build_full_name, Person};
+
+fn main() {
+  let john = Person::new("James", Some("Oliver"), "Smith");
+  let alice = Person::new("Alice", None, "Stevens");
+  let bob = Person::new("Robert", Some("Murdock"), "Jones");
+  for person in [&john, &alice, &bob] {
+    println!("{}", build_full_name(person));
+  }
+  println!();
+
+  // And the other way around, from text back to a Person
+  for text in ["James Oliver Smith", "Smith, James O.", "Dr. Alice Stevens Jr.", "Ludwig van Beethoven", "Dr. Who"] {
+    match parse_name(text) {
+      Ok(parsed) => print!("{}", parsed),
+      Err(error) => println!("{}: {}", text, error),
+    }
+  }
+  println!();
+
+  // The same Person written in other styles
+  println!("{}", format_name(&john, &NameStyle::LastFirst));
+  println!("{}", format_name(&john, &NameStyle::Initials));
+  println!("{}", format_name(&alice, &NameStyle::Formal { honorific: Some(String::from("Ms.")) }));
+  match "{last:upper} {first}[ {middle:initial}]".parse::<NameStyle>() {
+    Ok(style) => println!("{}", format_name(&bob, &style)),
+    Err(error) => println!("{}", error),
+  }
+
+  // Any number of given and family names, and the name someone prefers to be called by
+  let maria = Person::from_names(&["María", "José"], &["García", "López"]).with_preferred("Majo");
+  println!("{} is called {}", build_full_name(&maria), format_name(&maria, &NameStyle::Informal));
+
+  // Initials follow whole letters, not bytes or chars
+  println!("{} {} {}", initials(&Person::new("Jean-Luc", None, "Picard")),
+    initials(&Person::new("E\u{301}mile", None, "Zola")), monogram(&maria));
+  println!();
+
+  // Sorted like a phone book: by family name, with case and accents only breaking ties
+  let mut people = vec![bob, Person::new("Émile", None, "Zola"), alice, Person::new("Zoe", None, "adams"), john];
+  Collator::new(Locale::Root).sort(&mut people);
+  for person in &people {
+    println!("{}", format_name(person, &NameStyle::LastFirst));
+  }
+  println!();
+
+  // Bob M. Jones is probably Robert Murdock Jones written another way
+  let robert = Person::new("Robert", Some("Murdock"), "Jones");
+  match parse_name("Bob M. Jones").map(|parsed| parsed.person) {
+    Ok(bob_again) => match Deduplicator::default().check(&robert, &bob_again) {
+      Some(found) => print!("Robert Murdock Jones and Bob M. Jones match with {}", found),
+      None => println!("Robert Murdock Jones and Bob M. Jones don't match"),
+    },
+    Err(error) => println!("Bob M. Jones: {}", error),
+  }
+
+  // Person::new takes anything, Person::try_new says everything that is wrong at once
+  if let Err(error) = Person::try_new("", Some(""), "Sm1th ") {
+    println!("{}", error);
+  }
+}
@@ -0,0 +1,168 @@
+// A small address book of names, kept in a CSV file. See person/directory.rs for the file
+// format and how imports and searches work.
+//
+// Build:  rustc people.rs
+// Usage:
+//   ./people add <name>                         parse the name and add it
+//   ./people list [style]                       everyone, numbered, in a name style (last-first)
+//   ./people find <query>                       search every part of the names, typos included
+//   ./people remove <number>                    remove the person with that number in list
+//   ./people import <file> [header=column ...]  add everyone in a CSV file
+//   ./people export <csv|json>                  print the whole directory
+//
+// The directory is kept in people.csv in the current directory, or in the file named by
+// PEOPLE_FILE. Styles are the ones in person/format.rs, templates included:
+//   ./people add "Dr. Robert Murdock Jones"
+//   ./people list "{called} {last:upper}"
+//   ./people import contacts.csv Vorname=first Nachname=family
+
+mod person;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use person::collate::Collator;
+use person::dedup::Deduplicator;
+use person::directory::{Column, Directory, ImportError, Importer};
+use person::format::{format_name, NameStyle};
+use person::parse::parse_name;
+use person::validate::Validator;
+use person::build_full_name;
+
+const USAGE: &str = "usage:
+  people add <name>
+  people list [style]
+  people find <query>
+  people remove <number>
+  people import <file> [header=column ...]
+  people export <csv|json>";
+
+fn directory_file() -> PathBuf {
+  env::var_os("PEOPLE_FILE").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("people.csv"))
+}
+
+fn open() -> Result<Directory, String> {
+  let path = directory_file();
+  Directory::load(&path, Collator::default()).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn save(directory: &Directory) -> Result<(), String> {
+  let path = directory_file();
+  directory.save(&path).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn add(name: &str) -> Result<String, String> {
+  let parsed = parse_name(name).map_err(|error| format!("can't add '{}': {}", name, error))?;
+  for ambiguity in &parsed.ambiguities {
+    println!("note: {}", ambiguity);
+  }
+  // Composed, so "Émile" typed with a combining accent is found by a search for "Émile"
+  let person = Validator::new().trimming().composing().validate(parsed.person)
+    .map_err(|error| format!("can't add '{}': {}", name, error))?;
+  let mut directory = open()?;
+  // Still added, the user may know better, but they should hear about it
+  let deduplicator = Deduplicator::default();
+  for (index, other) in directory.people().iter().enumerate() {
+    if let Some(found) = deduplicator.check(&person, other) {
+      println!("possible duplicate of #{} {} (score {:.2})", index + 1, build_full_name(other), found.score);
+    }
+  }
+  let full_name = build_full_name(&person);
+  let index = directory.add(person);
+  save(&directory)?;
+  Ok(format!("added #{} {}", index + 1, full_name))
+}
+
+fn list(style: &str) -> Result<String, String> {
+  let style: NameStyle = style.parse().map_err(|error| format!("invalid style: {}", error))?;
+  let directory = open()?;
+  for (index, person) in directory.people().iter().enumerate() {
+    println!("{:>4}  {}", index + 1, format_name(person, &style));
+  }
+  Ok(format!("{} people", directory.len()))
+}
+
+fn find(query: &str) -> Result<String, String> {
+  let directory = open()?;
+  let hits = directory.search(query);
+  for hit in &hits {
+    let matched: Vec<String> = hit.matched.iter().map(|(field, part)| format!("{} {}", field.name(), part)).collect();
+    let person = &directory.people()[hit.index];
+    println!("{:>4}  {}  ({:.2}: {})", hit.index + 1, build_full_name(person), hit.score, matched.join(", "));
+  }
+  Ok(format!("{} found", hits.len()))
+}
+
+fn remove(number: &str) -> Result<String, String> {
+  let mut directory = open()?;
+  let removed = number.parse::<usize>().ok()
+    .and_then(|number| number.checked_sub(1))
+    .and_then(|index| directory.remove(index))
+    .ok_or(format!("there is no person #{}, see people list", number))?;
+  save(&directory)?;
+  Ok(format!("removed #{} {}", number, build_full_name(&removed)))
+}
+
+// Each mapping is written header=column, like Vorname=first
+fn import(file: &str, mappings: &[&str]) -> Result<String, String> {
+  let mut importer = Importer::new();
+  for mapping in mappings {
+    let (header, column) = mapping.split_once('=').ok_or(format!("expected header=column, got '{}'", mapping))?;
+    let column = Column::from_header(column).ok_or(format!("unknown column '{}'", column))?;
+    importer = importer.map(header, column);
+  }
+  let text = fs::read_to_string(file).map_err(|error| format!("{}: {}", file, error))?;
+  let import = importer.import(&text).map_err(|error| match error {
+    ImportError::NoNameColumns { .. } => format!("{}: {}, map one with header=column", file, error),
+    error => format!("{}: {}", file, error),
+  })?;
+  for error in &import.errors {
+    eprintln!("{}: skipped {}", file, error);
+  }
+  let mut directory = open()?;
+  let imported = import.people.len();
+  directory.extend(import.people);
+  save(&directory)?;
+  let mut summary = format!("imported {} people, skipped {} rows", imported, import.errors.len());
+  if !import.ignored.is_empty() {
+    summary.push_str(&format!(", ignored columns: {}", import.ignored.join(", ")));
+  }
+  Ok(summary)
+}
+
+fn export(format: &str) -> Result<String, String> {
+  let directory = open()?;
+  let text = match format {
+    "csv" => directory.to_csv(),
+    "json" => directory.to_json(),
+    _ => return Err(format!("unknown export format '{}', expected csv or json", format)),
+  };
+  Ok(text.trim_end().to_string())
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+  let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+  match args.as_slice() {
+    ["add", name @ ..] if !name.is_empty() => add(&name.join(" ")),
+    ["list"] => list("last-first"),
+    ["list", style] => list(style),
+    ["find", query @ ..] if !query.is_empty() => find(&query.join(" ")),
+    ["remove", number] => remove(number),
+    ["import", file, mappings @ ..] => import(file, mappings),
+    ["export", format] => export(format),
+    _ => Err(String::from(USAGE)),
+  }
+}
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  match run(&args) {
+    Ok(summary) => println!("{}", summary),
+    Err(message) => {
+      eprintln!("{}", message);
+      process::exit(1);
+    },
+  }
+}
@@ -0,0 +1,214 @@
+// ======================== SORTING PEOPLE ========================
+// Sorting names as plain strings puts "Zoe" before "adam" (uppercase letters come first in
+// Unicode) and "Émile" after "Zoe" (accented letters come after all of ASCII). People expect
+// a directory to sort like a phone book, so names are compared in three rounds, like the
+// Unicode Collation Algorithm does:
+//
+//   1. the letters themselves, ignoring case, accents, spaces and punctuation
+//   2. only if those are equal: the accents ("Muller" before "Müller")
+//   3. only if those are equal too: the case ("smith" before "Smith")
+//
+// People are sorted by family name, then given names. Some conventions file "van Beethoven"
+// under B instead of V, which ignore_particles turns on.
+//
+// Some languages sort letters differently, which the Locale picks:
+//   > Root: the default, accented letters sort with their base letter
+//   > German: the phone book rule, ä ö ü sort as ae oe ue
+//   > Swedish: å ä ö are their own letters after z
+//   > Spanish: ñ is its own letter after n
+//
+// Accents written as a separate combining mark (e followed by U+0301) count the same as the
+// single accented letter, in every round: the text is composed first, and the accent round
+// weighs a letter by the marks it is made of, so "é" weighs the same as "e" and U+0301.
+
+use std::cmp::Ordering;
+
+use super::marks::is_mark;
+use super::validate::{compose, decompose};
+use super::Person;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+  #[default]
+  Root,
+  German,
+  Swedish,
+  Spanish,
+}
+
+impl Locale {
+  // A language tag like "de", "sv-SE" or "es"
+  pub fn from_tag(tag: &str) -> Option<Locale> {
+    let language = tag.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+    match language.as_str() {
+      "" | "root" | "und" | "en" | "fr" | "it" | "nl" | "pt" => Some(Locale::Root),
+      "de" => Some(Locale::German),
+      "sv" | "fi" => Some(Locale::Swedish),
+      "es" => Some(Locale::Spanish),
+      _ => None,
+    }
+  }
+}
+
+// A letter with its accent taken off, or the letters it stands for ("æ" is "ae")
+pub fn base_letters(c: char) -> Option<&'static str> {
+  let base = match c {
+    'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+    'æ' => "ae",
+    'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+    'ď' | 'đ' | 'ð' => "d",
+    'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+    'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+    'ĥ' | 'ħ' => "h",
+    'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+    'ĳ' => "ij",
+    'ĵ' => "j",
+    'ķ' => "k",
+    'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+    'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+    'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+    'œ' => "oe",
+    'ŕ' | 'ŗ' | 'ř' => "r",
+    'ś' | 'ŝ' | 'ş' | 'š' => "s",
+    'ß' => "ss",
+    'ţ' | 'ť' | 'ŧ' => "t",
+    'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+    'ŵ' => "w",
+    'ý' | 'ÿ' | 'ŷ' => "y",
+    'ź' | 'ż' | 'ž' => "z",
+    'þ' => "th",
+    _ => return None,
+  };
+  Some(base)
+}
+
+// The accent weight of a letter: the marks it decomposes into, outermost last, or its own code
+// for a letter like "ø" whose accent can't be written as a mark
+fn accent(c: char) -> u32 {
+  match decompose(c) {
+    Some((base, mark)) => accent(base).wrapping_mul(0x400).wrapping_add(mark as u32),
+    None if base_letters(c).is_some() => c as u32,
+    None => 0,
+  }
+}
+
+// Letters get weight code * 4, so a locale can slot its own letters in between
+fn weight(c: char) -> u32 {
+  c as u32 * 4
+}
+
+const AFTER_Z: u32 = 'z' as u32 * 4;
+
+// The comparison key of one piece of text, one list per round
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct SortKey {
+  pub primary: Vec<u32>,
+  pub secondary: Vec<u32>,
+  pub tertiary: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Collator {
+  pub locale: Locale,
+  pub ignore_particles: bool,
+}
+
+impl Collator {
+  pub fn new(locale: Locale) -> Collator {
+    Collator { locale, ignore_particles: false }
+  }
+
+  pub fn ignoring_particles(mut self) -> Collator {
+    self.ignore_particles = true;
+    self
+  }
+
+  // The primary weights this locale gives a lowercase letter, when it treats it specially
+  fn tailored(&self, c: char) -> Option<Vec<u32>> {
+    let weights = match (self.locale, c) {
+      (Locale::German, 'ä') => vec![weight('a'), weight('e')],
+      (Locale::German, 'ö') => vec![weight('o'), weight('e')],
+      (Locale::German, 'ü') => vec![weight('u'), weight('e')],
+      (Locale::Swedish, 'å') => vec![AFTER_Z + 1],
+      (Locale::Swedish, 'ä' | 'æ') => vec![AFTER_Z + 2],
+      (Locale::Swedish, 'ö' | 'ø') => vec![AFTER_Z + 3],
+      (Locale::Spanish, 'ñ') => vec![weight('n') + 2],
+      _ => return None,
+    };
+    Some(weights)
+  }
+
+  pub fn key(&self, text: &str) -> SortKey {
+    let mut key = SortKey::default();
+    for c in compose(text).chars() {
+      // A mark left over that has no composed letter: it changes the accent of the letter before it
+      if is_mark(c) {
+        if let Some(last) = key.secondary.last_mut() {
+          *last = last.wrapping_mul(0x400).wrapping_add(c as u32);
+        }
+        continue;
+      }
+      if !c.is_alphanumeric() {
+        continue;
+      }
+      let lower = c.to_lowercase().next().unwrap_or(c);
+      let case = u8::from(c != lower);
+      let (primary, accent) = match (self.tailored(lower), base_letters(lower)) {
+        (Some(weights), _) => (weights, accent(lower)),
+        (None, Some(base)) => (base.chars().map(weight).collect(), accent(lower)),
+        (None, None) => (vec![weight(lower)], 0),
+      };
+      // Letters that expand ("æ" to "ae") share one accent and case mark
+      for (index, primary) in primary.into_iter().enumerate() {
+        key.primary.push(primary);
+        key.secondary.push(if index == 0 { accent } else { 0 });
+        key.tertiary.push(case);
+      }
+    }
+    key
+  }
+
+  pub fn compare_str(&self, a: &str, b: &str) -> Ordering {
+    let a = self.key(a);
+    let b = self.key(b);
+    a.primary.cmp(&b.primary).then(a.secondary.cmp(&b.secondary)).then(a.tertiary.cmp(&b.tertiary))
+  }
+
+  // What a person is filed under
+  fn family_text(&self, person: &Person) -> String {
+    let family = if self.ignore_particles { person.last() } else { person.family_name() };
+    family.unwrap_or_default()
+  }
+
+  pub fn person_key(&self, person: &Person) -> (SortKey, SortKey) {
+    (self.key(&self.family_text(person)), self.key(&person.given.join(" ")))
+  }
+
+  // people.sort_by(|a, b| collator.compare(a, b))
+  pub fn compare(&self, a: &Person, b: &Person) -> Ordering {
+    compare_person_keys(&self.person_key(a), &self.person_key(b))
+  }
+
+  // A stable sort, so people who compare equal keep their order. The keys are worked out
+  // once per person instead of once per comparison.
+  pub fn sort(&self, people: &mut [Person]) {
+    let mut keyed: Vec<((SortKey, SortKey), Person)> =
+      people.iter().map(|person| (self.person_key(person), person.clone())).collect();
+    keyed.sort_by(|(a, _), (b, _)| compare_person_keys(a, b));
+    for (slot, (_, person)) in people.iter_mut().zip(keyed) {
+      *slot = person;
+    }
+  }
+}
+
+// Each round over the family name and then the given names before the next round starts, so
+// an accent in the given name doesn't outweigh a different letter in the family name
+fn compare_person_keys(a: &(SortKey, SortKey), b: &(SortKey, SortKey)) -> Ordering {
+  let ((a_family, a_given), (b_family, b_given)) = (a, b);
+  a_family.primary.cmp(&b_family.primary)
+    .then(a_given.primary.cmp(&b_given.primary))
+    .then(a_family.secondary.cmp(&b_family.secondary))
+    .then(a_given.secondary.cmp(&b_given.secondary))
+    .then(a_family.tertiary.cmp(&b_family.tertiary))
+    .then(a_given.tertiary.cmp(&b_given.tertiary))
+}
@@ -0,0 +1,427 @@
+// ======================== FINDING DUPLICATES ========================
+// "Robert Murdock Jones" and "Bob M. Jones" are most likely the same person, but as strings
+// they are different. To find records like these, each part of the two names is compared on
+// its own and gets a verdict:
+//
+//   > Same          the same text
+//   > SameLetters   the same once case and accents are ignored: "Müller" and "muller"
+//   > Nickname      both forms of one name, from a table: "Bob" and "Robert"
+//   > Initial       one is the initial of the other: "M." and "Murdock"
+//   > Similar       close enough to be a typo, by Jaro-Winkler similarity: "Jonson" and "Johnson"
+//   > Missing       only one of the records has this part, which says nothing either way,
+//                   except for the family name: every record should have one, so a missing
+//                   family name counts as a different one. Otherwise "John" with no family
+//                   name would be a perfect match for both "John Smith" and "John Doe".
+//   > Different     none of the above
+//
+// The verdicts are weighted (the family name counts most, the middle names least) into a
+// score between 0 and 1. Two records with a score at the threshold or above are duplicates,
+// and the verdicts are kept as the explanation of why. Different suffixes ("Jr." and "Sr.")
+// halve the score, since they usually mean parent and child.
+//
+// clusters compares every pair of records, which is fine for an address book but not for
+// millions of records. A record joins a cluster through any one match, so "Robert Jones Jr."
+// and "Robert Jones Sr." can end up together through a plain "Robert Jones" that matches
+// both; the links say which pairs really matched.
+
+use std::fmt;
+
+use super::collate::base_letters;
+use super::marks::is_mark;
+use super::Person;
+
+// Formal names and the short forms people use for them
+const NICKNAMES: [(&str, &[&str]); 32] = [
+  ("alexander", &["alex", "sandy", "xander"]),
+  ("alexandra", &["alex", "sandra", "sandy"]),
+  ("andrew", &["andy", "drew"]),
+  ("anthony", &["tony"]),
+  ("benjamin", &["ben", "benny"]),
+  ("catherine", &["cathy", "kate", "katie", "kay"]),
+  ("charles", &["charlie", "chuck", "chas"]),
+  ("christopher", &["chris", "kit"]),
+  ("daniel", &["dan", "danny"]),
+  ("david", &["dave", "davy"]),
+  ("edward", &["ed", "eddie", "ted", "ned"]),
+  ("elizabeth", &["liz", "lizzie", "beth", "betty", "eliza", "bess"]),
+  ("henry", &["harry", "hank"]),
+  ("james", &["jim", "jimmy", "jamie"]),
+  ("jennifer", &["jen", "jenny"]),
+  ("john", &["jack", "johnny"]),
+  ("joseph", &["joe", "joey"]),
+  ("katherine", &["kathy", "kate", "katie", "kay"]),
+  ("margaret", &["maggie", "meg", "peggy", "marge"]),
+  ("matthew", &["matt"]),
+  ("michael", &["mike", "mikey", "mick"]),
+  ("nicholas", &["nick", "nicky"]),
+  ("patricia", &["pat", "patty", "trish"]),
+  ("richard", &["rick", "ricky", "dick", "rich"]),
+  ("robert", &["bob", "bobby", "rob", "robbie", "bert"]),
+  ("samuel", &["sam", "sammy"]),
+  ("stephen", &["steve", "stevie"]),
+  ("steven", &["steve", "stevie"]),
+  ("susan", &["sue", "susie"]),
+  ("theodore", &["ted", "teddy", "theo"]),
+  ("thomas", &["tom", "tommy"]),
+  ("william", &["bill", "billy", "will", "willy", "liam"]),
+];
+
+// How similar two names must be to count as a typo of each other
+const SIMILAR: f64 = 0.85;
+
+// Lowercase letters and digits with the accents taken off: "O'Brien-Müller" -> "obrienmuller"
+pub fn fold(text: &str) -> String {
+  let mut folded = String::new();
+  for c in text.chars().filter(|&c| !is_mark(c)) {
+    for lower in c.to_lowercase() {
+      match base_letters(lower) {
+        Some(base) => folded.push_str(base),
+        None if lower.is_alphanumeric() => folded.push(lower),
+        None => {},
+      }
+    }
+  }
+  folded
+}
+
+// "M." or "M" stand for a name starting with M
+fn as_initial(name: &str) -> Option<char> {
+  let mut letters = name.trim().trim_end_matches('.').chars();
+  match (letters.next(), letters.next()) {
+    (Some(letter), None) if letter.is_alphabetic() => fold(&letter.to_string()).chars().next(),
+    _ => None,
+  }
+}
+
+// The formal names a (folded) name can be short for, including itself
+fn formal_names(name: &str) -> Vec<&'static str> {
+  NICKNAMES.iter()
+    .filter(|(formal, short)| *formal == name || short.contains(&name))
+    .map(|(formal, _)| *formal)
+    .collect()
+}
+
+fn jaro(a: &[char], b: &[char]) -> f64 {
+  if a.is_empty() || b.is_empty() {
+    return if a.len() == b.len() { 1.0 } else { 0.0 };
+  }
+  // Letters count as matching when they are at most this far apart
+  let range = (a.len().max(b.len()) / 2).saturating_sub(1);
+  let mut used = vec![false; b.len()];
+  let mut a_matches = Vec::new();
+  for (i, &c) in a.iter().enumerate() {
+    let start = i.saturating_sub(range);
+    let end = (i + range + 1).min(b.len());
+    for j in start..end {
+      if !used[j] && b[j] == c {
+        used[j] = true;
+        a_matches.push(c);
+        break;
+      }
+    }
+  }
+  if a_matches.is_empty() {
+    return 0.0;
+  }
+  let b_matches = b.iter().zip(&used).filter(|(_, &used)| used).map(|(&c, _)| c);
+  let transpositions = a_matches.iter().zip(b_matches).filter(|(&x, y)| x != *y).count() as f64 / 2.0;
+  let matches = a_matches.len() as f64;
+  (matches / a.len() as f64 + matches / b.len() as f64 + (matches - transpositions) / matches) / 3.0
+}
+
+// Jaro-Winkler similarity, 1.0 for equal text and 0.0 for nothing in common. Names that start
+// the same way score higher, since typos are rarer at the start of a word.
+pub fn similarity(a: &str, b: &str) -> f64 {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+  let jaro = jaro(&a, &b);
+  let prefix = a.iter().zip(&b).take(4).take_while(|(x, y)| x == y).count() as f64;
+  jaro + prefix * 0.1 * (1.0 - jaro)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Part { First, Middle, Family, Suffix }
+
+impl Part {
+  // How much this part counts towards the score
+  fn weight(&self) -> f64 {
+    match self {
+      Part::First => 0.35,
+      Part::Middle => 0.15,
+      Part::Family => 0.5,
+      Part::Suffix => 0.0,
+    }
+  }
+}
+
+impl fmt::Display for Part {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Part::First => write!(f, "first name"),
+      Part::Middle => write!(f, "middle name"),
+      Part::Family => write!(f, "family name"),
+      Part::Suffix => write!(f, "suffix"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+  Same,
+  SameLetters,
+  Nickname { formal: &'static str },
+  Initial,
+  Similar(f64),
+  Missing,
+  Different,
+}
+
+impl Verdict {
+  // How much of the part's weight it earns, None if it doesn't count at all
+  fn fraction(&self) -> Option<f64> {
+    match self {
+      Verdict::Same => Some(1.0),
+      Verdict::SameLetters => Some(0.95),
+      Verdict::Nickname { .. } => Some(0.9),
+      Verdict::Initial => Some(0.75),
+      Verdict::Similar(similarity) => Some(*similarity),
+      Verdict::Missing => None,
+      Verdict::Different => Some(0.0),
+    }
+  }
+}
+
+// One part of the two names and what was found when comparing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reason {
+  pub part: Part,
+  pub a: String,
+  pub b: String,
+  pub verdict: Verdict,
+}
+
+impl fmt::Display for Reason {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let Reason { part, a, b, verdict } = self;
+    match verdict {
+      Verdict::Same => write!(f, "{}: both {}", part, a),
+      Verdict::SameLetters => write!(f, "{}: {} and {} only differ in case or accents", part, a, b),
+      Verdict::Nickname { formal } => {
+        let (long, short) = if fold(a) == *formal { (a, b) } else { (b, a) };
+        if fold(long) == *formal {
+          write!(f, "{}: {} is short for {}", part, short, long)
+        } else {
+          write!(f, "{}: {} and {} are both short for {}", part, a, b, capitalised(formal))
+        }
+      },
+      Verdict::Initial => {
+        let (initial, name) = if as_initial(a).is_some() { (a, b) } else { (b, a) };
+        write!(f, "{}: {} is the initial of {}", part, initial, name)
+      },
+      Verdict::Similar(similarity) => write!(f, "{}: {} and {} are similar ({:.2})", part, a, b, similarity),
+      Verdict::Missing => write!(f, "{}: only one record has one ({})", part, if a.is_empty() { b } else { a }),
+      Verdict::Different => write!(f, "{}: {} and {} are different", part, a, b),
+    }
+  }
+}
+
+fn capitalised(word: &str) -> String {
+  let mut chars = word.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+// The best verdict for two names, trying the cheapest explanation first
+fn compare_names(a: &str, b: &str, nicknames: bool) -> Verdict {
+  let (folded_a, folded_b) = (fold(a), fold(b));
+  if a.trim() == b.trim() {
+    return Verdict::Same;
+  }
+  if folded_a == folded_b {
+    return Verdict::SameLetters;
+  }
+  match (as_initial(a), as_initial(b)) {
+    // Equal initials were already caught as SameLetters
+    (Some(_), Some(_)) => return Verdict::Different,
+    (Some(initial), None) | (None, Some(initial)) => {
+      let name = if folded_a.chars().count() == 1 { &folded_b } else { &folded_a };
+      return if name.starts_with(initial) { Verdict::Initial } else { Verdict::Different };
+    },
+    (None, None) => {},
+  }
+  if nicknames {
+    let formal_b = formal_names(&folded_b);
+    if let Some(formal) = formal_names(&folded_a).into_iter().find(|formal| formal_b.contains(formal)) {
+      return Verdict::Nickname { formal };
+    }
+  }
+  let similarity = similarity(&folded_a, &folded_b);
+  if similarity >= SIMILAR {
+    Verdict::Similar(similarity)
+  } else {
+    Verdict::Different
+  }
+}
+
+// Compares every pair of candidates and keeps the one with the best verdict
+fn best_reason(part: Part, a: &[String], b: &[String], nicknames: bool) -> Reason {
+  let mut best: Option<Reason> = None;
+  for x in a {
+    for y in b {
+      let verdict = compare_names(x, y, nicknames);
+      let better = match &best {
+        Some(reason) => verdict.fraction() > reason.verdict.fraction(),
+        None => true,
+      };
+      if better {
+        best = Some(Reason { part, a: x.clone(), b: y.clone(), verdict });
+      }
+    }
+  }
+  best.unwrap_or_else(|| Reason {
+    part,
+    a: a.first().cloned().unwrap_or_default(),
+    b: b.first().cloned().unwrap_or_default(),
+    verdict: Verdict::Missing,
+  })
+}
+
+// The names someone may be listed under: their first name, nickname and preferred name
+fn first_names(person: &Person) -> Vec<String> {
+  let names = person.first().map(String::from).into_iter().chain(person.nickname.clone()).chain(person.preferred.clone());
+  names.filter(|name| !name.trim().is_empty()).collect()
+}
+
+fn family_names(person: &Person) -> Vec<String> {
+  person.family_name().into_iter().chain(person.last()).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+  pub score: f64,
+  pub reasons: Vec<Reason>,
+}
+
+impl fmt::Display for Match {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "score {:.2}", self.score)?;
+    for reason in &self.reasons {
+      writeln!(f, "  {}", reason)?;
+    }
+    Ok(())
+  }
+}
+
+pub fn compare(a: &Person, b: &Person) -> Match {
+  let mut reasons = vec![
+    best_reason(Part::Family, &family_names(a), &family_names(b), false),
+    best_reason(Part::First, &first_names(a), &first_names(b), true),
+  ];
+  // Middle names are compared in order; extra ones on one side are missing on the other
+  let (middle_a, middle_b) = (a.middle_names(), b.middle_names());
+  for i in 0..middle_a.len().max(middle_b.len()) {
+    let x: Vec<String> = middle_a.get(i).cloned().into_iter().collect();
+    let y: Vec<String> = middle_b.get(i).cloned().into_iter().collect();
+    reasons.push(best_reason(Part::Middle, &x, &y, true));
+  }
+  let suffix_a = a.suffix.iter().cloned().collect::<Vec<String>>();
+  let suffix_b = b.suffix.iter().cloned().collect::<Vec<String>>();
+  if !suffix_a.is_empty() && !suffix_b.is_empty() {
+    reasons.push(best_reason(Part::Suffix, &suffix_a, &suffix_b, false));
+  }
+
+  let middle_count = reasons.iter().filter(|reason| reason.part == Part::Middle).count().max(1) as f64;
+  let (mut earned, mut possible) = (0.0, 0.0);
+  for reason in &reasons {
+    let weight = match reason.part {
+      Part::Middle => reason.part.weight() / middle_count,
+      part => part.weight(),
+    };
+    let fraction = match (reason.part, reason.verdict.fraction()) {
+      (Part::Family, None) => Some(0.0),
+      (_, fraction) => fraction,
+    };
+    if let Some(fraction) = fraction {
+      earned += weight * fraction;
+      possible += weight;
+    }
+  }
+  let mut score = if possible > 0.0 { earned / possible } else { 0.0 };
+  let different_suffix = reasons.iter().any(|reason| reason.part == Part::Suffix && reason.verdict == Verdict::Different);
+  if different_suffix {
+    score /= 2.0;
+  }
+  Match { score, reasons }
+}
+
+// Records that are duplicates of each other, directly or through another record in the group
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+  // Indices into the list of records, in their original order
+  pub members: Vec<usize>,
+  // The pairs that matched, with why
+  pub links: Vec<(usize, usize, Match)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deduplicator {
+  pub threshold: f64,
+}
+
+impl Default for Deduplicator {
+  fn default() -> Deduplicator {
+    Deduplicator { threshold: 0.85 }
+  }
+}
+
+impl Deduplicator {
+  pub fn new(threshold: f64) -> Deduplicator {
+    Deduplicator { threshold }
+  }
+
+  // The match when the two are duplicates, None when they aren't
+  pub fn check(&self, a: &Person, b: &Person) -> Option<Match> {
+    Some(compare(a, b)).filter(|found| found.score >= self.threshold)
+  }
+
+  // Only groups with more than one record, ordered by their first member
+  pub fn clusters(&self, people: &[Person]) -> Vec<Cluster> {
+    // Union-find: every record points towards the first record of its group
+    let mut group: Vec<usize> = (0..people.len()).collect();
+    fn root(group: &[usize], mut i: usize) -> usize {
+      while group[i] != i {
+        i = group[i];
+      }
+      i
+    }
+    let mut links = Vec::new();
+    for i in 0..people.len() {
+      for j in i + 1..people.len() {
+        if let Some(found) = self.check(&people[i], &people[j]) {
+          let (x, y) = (root(&group, i), root(&group, j));
+          group[x.max(y)] = x.min(y);
+          links.push((i, j, found));
+        }
+      }
+    }
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for i in 0..people.len() {
+      let first = root(&group, i);
+      match clusters.iter_mut().find(|cluster| cluster.members[0] == first) {
+        Some(cluster) => cluster.members.push(i),
+        None => clusters.push(Cluster { members: vec![i], links: Vec::new() }),
+      }
+    }
+    for (i, j, found) in links {
+      let first = root(&group, i);
+      if let Some(cluster) = clusters.iter_mut().find(|cluster| cluster.members[0] == first) {
+        cluster.links.push((i, j, found));
+      }
+    }
+    clusters.retain(|cluster| cluster.members.len() > 1);
+    clusters
+  }
+}
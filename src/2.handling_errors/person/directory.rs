@@ -0,0 +1,597 @@
+// ======================== PEOPLE DIRECTORY ========================
+// A Directory is a list of people kept in phone book order (see collate.rs), which can be
+// saved to and loaded from a CSV file, exported as JSON and searched.
+//
+// CSV files from other programs name their columns in all kinds of ways, so the header row is
+// mapped onto Columns: "First Name", "first_name" and "Forename" all mean Column::First, and
+// Importer::map adds names it doesn't know ("Vorname" -> First). A "Name" column holds the
+// whole name and goes through parse_name; the other columns then fill in or replace its parts.
+//
+// One bad row shouldn't stop the import of a thousand good ones, so each row that fails is
+// reported with its line number and the rest are imported. A header that doesn't make sense
+// fails the whole import. Imported people go through the same Validator as `people add`, so a
+// row that couldn't have been added by hand is a failed row too.
+//
+// Columns holding several names (given, middle, family) split them on spaces, so a family
+// name with a space in it comes back as two family names.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::collate::Collator;
+use super::dedup::{fold, similarity};
+use super::format::NameField;
+use super::parse::{parse_name, NameParseError};
+use super::validate::{ValidationError, Validator};
+use super::Person;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column { Prefix, Given, First, Middle, Particle, Family, Suffix, Nickname, Preferred, FullName }
+
+impl Column {
+  // The columns a Directory saves, in order
+  pub const SAVED: [Column; 7] = [
+    Column::Prefix, Column::Given, Column::Particle, Column::Family, Column::Suffix, Column::Nickname, Column::Preferred,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Column::Prefix => "prefix",
+      Column::Given => "given",
+      Column::First => "first",
+      Column::Middle => "middle",
+      Column::Particle => "particle",
+      Column::Family => "family",
+      Column::Suffix => "suffix",
+      Column::Nickname => "nickname",
+      Column::Preferred => "preferred",
+      Column::FullName => "name",
+    }
+  }
+
+  // Header text as other programs write it, ignoring case, spaces and punctuation
+  pub fn from_header(header: &str) -> Option<Column> {
+    let key: String = header.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect();
+    let column = match key.as_str() {
+      "prefix" | "title" | "honorific" | "salutation" => Column::Prefix,
+      "given" | "givennames" | "forenames" => Column::Given,
+      "first" | "firstname" | "givenname" | "forename" => Column::First,
+      "middle" | "middlename" | "middlenames" | "middleinitial" => Column::Middle,
+      "particle" => Column::Particle,
+      "family" | "familyname" | "familynames" | "last" | "lastname" | "surname" | "surnames" => Column::Family,
+      "suffix" | "generation" => Column::Suffix,
+      "nickname" | "nick" | "knownas" | "aka" => Column::Nickname,
+      "preferred" | "preferredname" => Column::Preferred,
+      "name" | "fullname" | "displayname" => Column::FullName,
+      _ => return None,
+    };
+    Some(column)
+  }
+}
+
+impl fmt::Display for Column {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+// ======================== CSV ========================
+// Fields are separated by commas. A field in double quotes may hold commas, line breaks and
+// quotes, the last written twice: "Jones, ""Bob""". Fields that need it are quoted on export.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowErrorKind {
+  // The quote is never closed, so the rest of the file was swallowed into this row
+  UnclosedQuote,
+  FieldCount { expected: usize, found: usize },
+  Name(NameParseError),
+  // None of the columns had a given or family name in them
+  NoName,
+  Invalid(ValidationError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+  pub line: usize,
+  pub kind: RowErrorKind,
+}
+
+impl fmt::Display for RowError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: ", self.line)?;
+    match &self.kind {
+      RowErrorKind::UnclosedQuote => write!(f, "a quote is never closed"),
+      RowErrorKind::FieldCount { expected, found } => write!(f, "expected {} fields, found {}", expected, found),
+      RowErrorKind::Name(error) => write!(f, "{}", error),
+      RowErrorKind::NoName => write!(f, "the row has no name in it"),
+      RowErrorKind::Invalid(error) => write!(f, "{}", error),
+    }
+  }
+}
+
+impl std::error::Error for RowError {}
+
+type Record = (usize, Result<Vec<String>, RowErrorKind>);
+
+// The records of a CSV text with the line each one starts on. Blank lines are skipped.
+fn csv_records(text: &str) -> Vec<Record> {
+  let mut records = Vec::new();
+  let mut chars = text.chars().peekable();
+  let mut line = 1;
+  while chars.peek().is_some() {
+    let start = line;
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    let ended = loop {
+      match chars.next() {
+        None => break !quoted,
+        Some('"') if quoted => {
+          if chars.peek() == Some(&'"') {
+            chars.next();
+            field.push('"');
+          } else {
+            quoted = false;
+          }
+        },
+        Some(c) if quoted => {
+          if c == '\n' {
+            line += 1;
+          }
+          field.push(c);
+        },
+        Some('"') if !started => {
+          quoted = true;
+          started = true;
+        },
+        Some(',') => {
+          fields.push(std::mem::take(&mut field));
+          started = false;
+        },
+        Some('\r') if chars.peek() == Some(&'\n') => {},
+        Some('\n') => {
+          line += 1;
+          break true;
+        },
+        Some(c) => {
+          field.push(c);
+          started = true;
+        },
+      }
+    };
+    fields.push(field);
+    if !ended {
+      records.push((start, Err(RowErrorKind::UnclosedQuote)));
+    } else if fields.len() > 1 || !fields[0].trim().is_empty() {
+      records.push((start, Ok(fields)));
+    }
+  }
+  records
+}
+
+fn csv_field(value: &str) -> String {
+  let plain = !value.contains([',', '"', '\n', '\r']) && value.trim() == value;
+  if plain {
+    value.to_string()
+  } else {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+  Empty,
+  // The header row itself is broken
+  Header(RowErrorKind),
+  // No column could hold a given or family name
+  NoNameColumns { headers: Vec<String> },
+  DuplicateColumn { header: String, column: Column },
+}
+
+impl fmt::Display for ImportError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ImportError::Empty => write!(f, "the file has no header row"),
+      ImportError::Header(kind) => write!(f, "{}", RowError { line: 1, kind: kind.clone() }),
+      ImportError::NoNameColumns { headers } => {
+        write!(f, "none of the columns ({}) holds a given or family name", headers.join(", "))
+      },
+      ImportError::DuplicateColumn { header, column } => {
+        write!(f, "column '{}' is the {} column again", header, column)
+      },
+    }
+  }
+}
+
+impl std::error::Error for ImportError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Import {
+  pub people: Vec<Person>,
+  pub errors: Vec<RowError>,
+  // Headers that didn't map to any column, their values are skipped
+  pub ignored: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Importer {
+  mapping: Vec<(String, Column)>,
+}
+
+impl Importer {
+  pub fn new() -> Importer {
+    Importer::default()
+  }
+
+  // Read the column with this exact header as `column`, before the usual header names
+  pub fn map(mut self, header: &str, column: Column) -> Importer {
+    self.mapping.push((header.trim().to_string(), column));
+    self
+  }
+
+  fn column(&self, header: &str) -> Option<Column> {
+    let mapped = self.mapping.iter().find(|(name, _)| name == header.trim()).map(|(_, column)| *column);
+    mapped.or_else(|| Column::from_header(header))
+  }
+
+  pub fn import(&self, text: &str) -> Result<Import, ImportError> {
+    let mut records = csv_records(text.trim_start_matches('\u{feff}')).into_iter();
+    let headers = match records.next() {
+      Some((_, Ok(headers))) => headers,
+      Some((_, Err(kind))) => return Err(ImportError::Header(kind)),
+      None => return Err(ImportError::Empty),
+    };
+
+    let mut import = Import::default();
+    let mut columns: Vec<Option<Column>> = Vec::new();
+    for header in &headers {
+      let column = self.column(header);
+      match column {
+        Some(column) if columns.contains(&Some(column)) => {
+          return Err(ImportError::DuplicateColumn { header: header.clone(), column });
+        },
+        Some(_) => {},
+        None => import.ignored.push(header.clone()),
+      }
+      columns.push(column);
+    }
+    let names = [Column::Given, Column::First, Column::Family, Column::FullName];
+    if !columns.iter().flatten().any(|column| names.contains(column)) {
+      return Err(ImportError::NoNameColumns { headers });
+    }
+
+    for (line, record) in records {
+      let person = record.and_then(|fields| {
+        if fields.len() != columns.len() {
+          return Err(RowErrorKind::FieldCount { expected: columns.len(), found: fields.len() });
+        }
+        let values: Vec<(Column, &str)> = columns.iter().zip(&fields)
+          .filter_map(|(column, value)| column.map(|column| (column, value.trim())))
+          .filter(|(_, value)| !value.is_empty())
+          .collect();
+        person_from_row(&values)
+      });
+      match person {
+        Ok(person) => import.people.push(person),
+        Err(kind) => import.errors.push(RowError { line, kind }),
+      }
+    }
+    Ok(import)
+  }
+}
+
+// A whole name goes first, so the columns with one part in them can correct it
+fn person_from_row(values: &[(Column, &str)]) -> Result<Person, RowErrorKind> {
+  let value = |wanted: Column| values.iter().find(|(column, _)| *column == wanted).map(|(_, value)| *value);
+  let words = |text: &str| text.split_whitespace().map(String::from).collect::<Vec<String>>();
+  let mut person = match value(Column::FullName) {
+    Some(name) => parse_name(name).map_err(RowErrorKind::Name)?.person,
+    None => Person::default(),
+  };
+
+  if let Some(given) = value(Column::Given) {
+    person.given = words(given);
+  }
+  if let Some(first) = value(Column::First) {
+    let middle = person.middle_names().to_vec();
+    person.given = std::iter::once(first.to_string()).chain(middle).collect();
+  }
+  if let Some(middle) = value(Column::Middle) {
+    let first = person.given.first().cloned().unwrap_or_default();
+    person.given = std::iter::once(first).chain(words(middle)).collect();
+  }
+  if let Some(family) = value(Column::Family) {
+    person.family = words(family);
+  }
+  for (column, slot) in [
+    (Column::Prefix, &mut person.prefix),
+    (Column::Particle, &mut person.particle),
+    (Column::Suffix, &mut person.suffix),
+    (Column::Nickname, &mut person.nickname),
+    (Column::Preferred, &mut person.preferred),
+  ] {
+    if let Some(text) = value(column) {
+      *slot = Some(text.to_string());
+    }
+  }
+
+  if person.first().is_none() && person.last().is_none() {
+    return Err(RowErrorKind::NoName);
+  }
+  Validator::new().trimming().composing().validate(person).map_err(RowErrorKind::Invalid)
+}
+
+// ======================== EXPORT ========================
+
+fn saved_value(person: &Person, column: Column) -> Option<String> {
+  let value = match column {
+    Column::Prefix => person.prefix.clone(),
+    Column::Given => Some(person.given.join(" ")),
+    Column::Particle => person.particle.clone(),
+    Column::Family => Some(person.family.join(" ")),
+    Column::Suffix => person.suffix.clone(),
+    Column::Nickname => person.nickname.clone(),
+    Column::Preferred => person.preferred.clone(),
+    Column::First | Column::Middle | Column::FullName => None,
+  };
+  value.filter(|value| !value.trim().is_empty())
+}
+
+pub fn to_csv(people: &[Person]) -> String {
+  let header: Vec<&str> = Column::SAVED.iter().map(|column| column.name()).collect();
+  let mut csv = header.join(",");
+  csv.push('\n');
+  for person in people {
+    let fields: Vec<String> =
+      Column::SAVED.iter().map(|&column| csv_field(&saved_value(person, column).unwrap_or_default())).collect();
+    csv.push_str(&fields.join(","));
+    csv.push('\n');
+  }
+  csv
+}
+
+fn json_string(text: &str) -> String {
+  let mut json = String::from("\"");
+  for c in text.chars() {
+    match c {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+      c => json.push(c),
+    }
+  }
+  json.push('"');
+  json
+}
+
+fn json_optional(value: &Option<String>) -> String {
+  value.as_deref().map(json_string).unwrap_or_else(|| String::from("null"))
+}
+
+fn json_list(names: &[String]) -> String {
+  let names: Vec<String> = names.iter().map(|name| json_string(name)).collect();
+  format!("[{}]", names.join(", "))
+}
+
+// An array with one object per person, one person per line
+pub fn to_json(people: &[Person]) -> String {
+  let objects: Vec<String> = people.iter().map(|person| {
+    format!(
+      "  {{\"prefix\": {}, \"given\": {}, \"particle\": {}, \"family\": {}, \"suffix\": {}, \"nickname\": {}, \"preferred\": {}}}",
+      json_optional(&person.prefix), json_list(&person.given), json_optional(&person.particle), json_list(&person.family),
+      json_optional(&person.suffix), json_optional(&person.nickname), json_optional(&person.preferred),
+    )
+  }).collect();
+  if objects.is_empty() {
+    String::from("[]\n")
+  } else {
+    format!("[\n{}\n]\n", objects.join(",\n"))
+  }
+}
+
+// ======================== DIRECTORY ========================
+
+#[derive(Debug)]
+pub enum DirectoryError {
+  Io(io::Error),
+  Import(ImportError),
+  // The directory's own file should never have bad rows, so they are an error too
+  Rows(Vec<RowError>),
+}
+
+impl fmt::Display for DirectoryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      DirectoryError::Io(error) => write!(f, "{}", error),
+      DirectoryError::Import(error) => write!(f, "{}", error),
+      DirectoryError::Rows(errors) => {
+        let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        write!(f, "{}", errors.join("\n"))
+      },
+    }
+  }
+}
+
+impl std::error::Error for DirectoryError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      DirectoryError::Io(error) => Some(error),
+      DirectoryError::Import(error) => Some(error),
+      DirectoryError::Rows(errors) => errors.first().map(|error| error as &(dyn std::error::Error + 'static)),
+    }
+  }
+}
+
+impl From<io::Error> for DirectoryError {
+  fn from(error: io::Error) -> DirectoryError {
+    DirectoryError::Io(error)
+  }
+}
+
+impl From<ImportError> for DirectoryError {
+  fn from(error: ImportError) -> DirectoryError {
+    DirectoryError::Import(error)
+  }
+}
+
+// One search result: which person, how well they matched and on which parts of the name
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+  pub index: usize,
+  pub score: f64,
+  pub matched: Vec<(NameField, String)>,
+}
+
+// How similar a word must be to a name part to be found by a misspelt search
+const FUZZY: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Directory {
+  people: Vec<Person>,
+  collator: Collator,
+}
+
+impl Directory {
+  pub fn new(collator: Collator) -> Directory {
+    Directory { people: Vec::new(), collator }
+  }
+
+  // A file that doesn't exist yet is an empty directory
+  pub fn load(path: &Path, collator: Collator) -> Result<Directory, DirectoryError> {
+    let text = match fs::read_to_string(path) {
+      Ok(text) => text,
+      Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Directory::new(collator)),
+      Err(error) => return Err(error.into()),
+    };
+    let import = Importer::new().import(&text)?;
+    if !import.errors.is_empty() {
+      return Err(DirectoryError::Rows(import.errors));
+    }
+    let mut directory = Directory::new(collator);
+    directory.extend(import.people);
+    Ok(directory)
+  }
+
+  // Written next to the file first and then renamed over it, so a crash halfway through
+  // never leaves half a directory behind
+  pub fn save(&self, path: &Path) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, self.to_csv())?;
+    fs::rename(&temporary, path)
+  }
+
+  pub fn people(&self) -> &[Person] {
+    &self.people
+  }
+
+  pub fn len(&self) -> usize {
+    self.people.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.people.is_empty()
+  }
+
+  // Puts the person in their place in the order and returns the index they got.
+  // Someone equal to a person already there goes after them.
+  pub fn add(&mut self, person: Person) -> usize {
+    let index = self.people.partition_point(|other| self.collator.compare(other, &person).is_le());
+    self.people.insert(index, person);
+    index
+  }
+
+  pub fn extend(&mut self, people: impl IntoIterator<Item = Person>) {
+    self.people.extend(people);
+    self.collator.sort(&mut self.people);
+  }
+
+  pub fn remove(&mut self, index: usize) -> Option<Person> {
+    if index < self.people.len() {
+      Some(self.people.remove(index))
+    } else {
+      None
+    }
+  }
+
+  pub fn to_csv(&self) -> String {
+    to_csv(&self.people)
+  }
+
+  pub fn to_json(&self) -> String {
+    to_json(&self.people)
+  }
+
+  // Every word of the query has to match some part of the name, either as the start of it
+  // ("Bee" finds Beethoven) or as a likely misspelling of it ("Bethoven"). Accents and case
+  // don't matter. The best matches come first, people who match equally well stay in order.
+  pub fn search(&self, query: &str) -> Vec<SearchHit> {
+    let words: Vec<String> = query.split_whitespace().map(fold).filter(|word| !word.is_empty()).collect();
+    if words.is_empty() {
+      return Vec::new();
+    }
+    let mut hits = Vec::new();
+    for (index, person) in self.people.iter().enumerate() {
+      let parts = name_parts(person);
+      let mut matched = Vec::new();
+      let mut total = 0.0;
+      for word in &words {
+        let best = parts.iter()
+          .map(|(field, part)| (field, part, word_score(word, &fold(part))))
+          .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+        match best {
+          Some((field, part, score)) if score > 0.0 => {
+            total += score;
+            matched.push((*field, part.clone()));
+          },
+          _ => break,
+        }
+      }
+      if matched.len() == words.len() {
+        hits.push(SearchHit { index, score: total / words.len() as f64, matched });
+      }
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits
+  }
+}
+
+// Every single name a person has, each with the field it comes from
+fn name_parts(person: &Person) -> Vec<(NameField, String)> {
+  let mut parts = Vec::new();
+  for (index, given) in person.given.iter().enumerate() {
+    parts.push((if index == 0 { NameField::First } else { NameField::Middle }, given.clone()));
+  }
+  parts.extend(person.family.iter().map(|family| (NameField::Last, family.clone())));
+  for (field, value) in [
+    (NameField::Prefix, &person.prefix),
+    (NameField::Particle, &person.particle),
+    (NameField::Suffix, &person.suffix),
+    (NameField::Nickname, &person.nickname),
+    (NameField::Preferred, &person.preferred),
+  ] {
+    parts.extend(value.iter().map(|value| (field, value.clone())));
+  }
+  parts.retain(|(_, part)| !part.trim().is_empty());
+  parts
+}
+
+// 1.0 when the part starts with the word, the similarity when it looks like a misspelling,
+// 0.0 otherwise
+fn word_score(word: &str, part: &str) -> f64 {
+  if part.starts_with(word) {
+    return 1.0;
+  }
+  let similarity = similarity(word, part);
+  if similarity >= FUZZY {
+    similarity
+  } else {
+    0.0
+  }
+}
@@ -0,0 +1,345 @@
+// ======================== FORMATTING NAMES ========================
+// build_full_name always writes "first middle last". Letters, lists and forms want other
+// orders, so a NameStyle picks one:
+//
+//   > Full          Dr. James Oliver Smith Jr.
+//   > LastFirst     Smith, James O., Jr.
+//   > Initials      J.O.S.
+//   > FamilyFirst   Smith James Oliver (Hungarian and East Asian order: Bartók Béla)
+//   > Formal        Dr. Smith Jr. (the person's own title, or one we give it)
+//   > Informal      Jim Smith (the name they prefer to be called by, and the family name)
+//   > Template      anything else, written as a template
+//
+// Templates are text with {fields} in it. Parts in [square brackets] are only written when
+// every field inside them has a value, which is how the missing middle name is handled:
+//
+//   "{last}, {first}[ {middle:initials}]"   ->  "Smith, James O." or "Stevens, Alice"
+//
+// Fields: prefix, given (first and middle), first, middle, particle, last (the family names),
+// family (particle and last), suffix, nickname, preferred, called (preferred, or else first).
+// After a colon: initial (first letter, "J."), initials (of every word, "O.M."), upper, lower.
+// Initials are taken from whole letters, accents and all, see initials.rs.
+// Put a backslash before a bracket to write the bracket itself: \{ \} \[ \]
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use super::initials::{self, initial};
+use super::Person;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameField { Prefix, Given, First, Middle, Particle, Last, Family, Suffix, Nickname, Preferred, Called }
+
+impl NameField {
+  pub const ALL: [NameField; 11] = [
+    NameField::Prefix, NameField::Given, NameField::First, NameField::Middle, NameField::Particle, NameField::Last,
+    NameField::Family, NameField::Suffix, NameField::Nickname, NameField::Preferred, NameField::Called,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      NameField::Prefix => "prefix",
+      NameField::Given => "given",
+      NameField::First => "first",
+      NameField::Middle => "middle",
+      NameField::Particle => "particle",
+      NameField::Last => "last",
+      NameField::Family => "family",
+      NameField::Suffix => "suffix",
+      NameField::Nickname => "nickname",
+      NameField::Preferred => "preferred",
+      NameField::Called => "called",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<NameField> {
+    NameField::ALL.iter().copied().find(|field| field.name() == name)
+  }
+
+  // None when the person doesn't have this part, an empty string counts as not having it
+  pub fn value(&self, person: &Person) -> Option<String> {
+    let value = match self {
+      NameField::Prefix => person.prefix.clone(),
+      NameField::Given => super::joined(&person.given),
+      NameField::First => person.first().map(String::from),
+      NameField::Middle => person.middle(),
+      NameField::Particle => person.particle.clone(),
+      NameField::Last => person.last(),
+      NameField::Family => person.family_name(),
+      NameField::Suffix => person.suffix.clone(),
+      NameField::Nickname => person.nickname.clone(),
+      NameField::Preferred => person.preferred.clone(),
+      NameField::Called => person.called().map(String::from),
+    };
+    value.filter(|value| !value.trim().is_empty())
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier { Initial, Initials, Upper, Lower }
+
+impl Modifier {
+  pub fn from_name(name: &str) -> Option<Modifier> {
+    match name {
+      "initial" => Some(Modifier::Initial),
+      "initials" => Some(Modifier::Initials),
+      "upper" => Some(Modifier::Upper),
+      "lower" => Some(Modifier::Lower),
+      _ => None,
+    }
+  }
+
+  fn apply(&self, value: &str) -> String {
+    match self {
+      Modifier::Initial => value.split_whitespace().next().map(initial).unwrap_or_default(),
+      Modifier::Initials => value.split_whitespace().map(initial).collect(),
+      Modifier::Upper => value.to_uppercase(),
+      Modifier::Lower => value.to_lowercase(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+  Text(String),
+  Field(NameField, Option<Modifier>),
+  // Written only when every field inside has a value
+  Optional(Vec<Piece>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+  Unclosed { position: usize, bracket: char },
+  Unmatched { position: usize, bracket: char },
+  UnknownField { position: usize, name: String },
+  UnknownModifier { position: usize, name: String },
+  // Neither a style name nor a template, which always has a { in it
+  UnknownStyle { name: String },
+}
+
+impl fmt::Display for TemplateError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TemplateError::Unclosed { position, bracket } => write!(f, "'{}' at position {} is never closed", bracket, position),
+      TemplateError::Unmatched { position, bracket } => {
+        write!(f, "'{}' at position {} closes nothing, write \\{} to use it as text", bracket, position, bracket)
+      },
+      TemplateError::UnknownField { position, name } => {
+        let names: Vec<&str> = NameField::ALL.iter().map(|field| field.name()).collect();
+        write!(f, "unknown field '{}' at position {}, expected one of {}", name, position, names.join(", "))
+      },
+      TemplateError::UnknownModifier { position, name } => {
+        write!(f, "unknown modifier '{}' at position {}, expected initial, initials, upper or lower", name, position)
+      },
+      TemplateError::UnknownStyle { name } => {
+        write!(f, "unknown style '{}', expected one of {} or a template like {{first}} {{last}}", name, STYLE_NAMES.join(", "))
+      },
+    }
+  }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+  source: String,
+  pieces: Vec<Piece>,
+}
+
+impl Template {
+  pub fn parse(source: &str) -> Result<Template, TemplateError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut position = 0;
+    let pieces = parse_pieces(&chars, &mut position, None)?;
+    Ok(Template { source: source.to_string(), pieces })
+  }
+
+  pub fn render(&self, person: &Person) -> String {
+    render_pieces(&self.pieces, person).0
+  }
+}
+
+impl fmt::Display for Template {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.source)
+  }
+}
+
+// Reads pieces until the end of the template, or until `closing` ends an optional part
+fn parse_pieces(chars: &[char], position: &mut usize, closing: Option<usize>) -> Result<Vec<Piece>, TemplateError> {
+  let mut pieces = Vec::new();
+  let mut text = String::new();
+  while *position < chars.len() {
+    let c = chars[*position];
+    match c {
+      '\\' if *position + 1 < chars.len() => {
+        text.push(chars[*position + 1]);
+        *position += 2;
+      },
+      '{' => {
+        let start = *position;
+        let end = chars[start..].iter().position(|&other| other == '}')
+          .ok_or(TemplateError::Unclosed { position: start, bracket: '{' })?;
+        let inside: String = chars[start + 1..start + end].iter().collect();
+        let (name, modifier) = match inside.split_once(':') {
+          Some((name, modifier)) => (name.trim(), Some(modifier.trim())),
+          None => (inside.trim(), None),
+        };
+        let field = NameField::from_name(name)
+          .ok_or(TemplateError::UnknownField { position: start, name: name.to_string() })?;
+        let modifier = match modifier {
+          Some(modifier) => Some(Modifier::from_name(modifier)
+            .ok_or(TemplateError::UnknownModifier { position: start, name: modifier.to_string() })?),
+          None => None,
+        };
+        if !text.is_empty() {
+          pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(Piece::Field(field, modifier));
+        *position = start + end + 1;
+      },
+      '[' => {
+        if !text.is_empty() {
+          pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        let start = *position;
+        *position += 1;
+        pieces.push(Piece::Optional(parse_pieces(chars, position, Some(start))?));
+      },
+      ']' if closing.is_some() => {
+        *position += 1;
+        if !text.is_empty() {
+          pieces.push(Piece::Text(text));
+        }
+        return Ok(pieces);
+      },
+      '}' | ']' => return Err(TemplateError::Unmatched { position: *position, bracket: c }),
+      _ => {
+        text.push(c);
+        *position += 1;
+      },
+    }
+  }
+  if let Some(start) = closing {
+    return Err(TemplateError::Unclosed { position: start, bracket: '[' });
+  }
+  if !text.is_empty() {
+    pieces.push(Piece::Text(text));
+  }
+  Ok(pieces)
+}
+
+// The text and whether every field had a value. A missing field drops the optional part
+// around it; at the top level it just writes nothing.
+fn render_pieces(pieces: &[Piece], person: &Person) -> (String, bool) {
+  let mut output = String::new();
+  let mut complete = true;
+  for piece in pieces {
+    match piece {
+      Piece::Text(text) => output.push_str(text),
+      Piece::Field(field, modifier) => match field.value(person) {
+        Some(value) => output.push_str(&modifier.map(|modifier| modifier.apply(&value)).unwrap_or(value)),
+        None => complete = false,
+      },
+      Piece::Optional(inner) => {
+        if let (text, true) = render_pieces(inner, person) {
+          output.push_str(&text);
+        }
+      },
+    }
+  }
+  (output, complete)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameStyle {
+  Full,
+  LastFirst,
+  Initials,
+  FamilyFirst,
+  // Uses the person's own prefix unless an honorific is given here
+  Formal { honorific: Option<String> },
+  Informal,
+  Template(Template),
+}
+
+// The built-in styles that are templates. Full and Informal aren't because any part of them
+// may be missing, even the first or last name, and Initials takes one from every given and
+// family name however many there are.
+struct BuiltinTemplates {
+  last_first: Template,
+  family_first: Template,
+  formal: Template,
+}
+
+// Parsed the first time a built-in style is used. They are fixed text, so one that doesn't
+// parse is a mistake in this file and panics instead of quietly writing nothing.
+fn builtin_templates() -> &'static BuiltinTemplates {
+  static TEMPLATES: OnceLock<BuiltinTemplates> = OnceLock::new();
+  TEMPLATES.get_or_init(|| {
+    let parse = |source: &str| {
+      Template::parse(source).unwrap_or_else(|error| panic!("built-in template \"{}\": {}", source, error))
+    };
+    BuiltinTemplates {
+      last_first: parse("{family}[, {first}[ {middle:initials}]][, {suffix}]"),
+      family_first: parse("{family}[ {first}][ {middle}]"),
+      formal: parse("[{prefix} ]{family}[ {suffix}]"),
+    }
+  })
+}
+
+const STYLE_NAMES: [&str; 6] = ["full", "last-first", "initials", "family-first", "formal", "informal"];
+
+impl FromStr for NameStyle {
+  type Err = TemplateError;
+
+  // A style name, or anything with a { in it as a template
+  fn from_str(text: &str) -> Result<NameStyle, TemplateError> {
+    let style = match text {
+      "full" => NameStyle::Full,
+      "last-first" => NameStyle::LastFirst,
+      "initials" => NameStyle::Initials,
+      "family-first" => NameStyle::FamilyFirst,
+      "formal" => NameStyle::Formal { honorific: None },
+      "informal" => NameStyle::Informal,
+      _ if text.contains('{') => NameStyle::Template(Template::parse(text)?),
+      _ => return Err(TemplateError::UnknownStyle { name: text.to_string() }),
+    };
+    Ok(style)
+  }
+}
+
+pub fn format_name(person: &Person, style: &NameStyle) -> String {
+  match style {
+    NameStyle::Full => {
+      let fields = [NameField::Prefix, NameField::Given, NameField::Family, NameField::Suffix];
+      let parts: Vec<String> = fields.iter().filter_map(|field| field.value(person)).collect();
+      parts.join(" ")
+    },
+    NameStyle::Informal => person.display_name(),
+    NameStyle::Initials => initials::initials(person),
+    NameStyle::Template(template) => template.render(person),
+    // These start with the family name, so without one they write the full name instead of
+    // ", James O."
+    NameStyle::LastFirst | NameStyle::FamilyFirst if NameField::Family.value(person).is_none() => {
+      format_name(person, &NameStyle::Full)
+    },
+    NameStyle::LastFirst => builtin_templates().last_first.render(person),
+    NameStyle::FamilyFirst => builtin_templates().family_first.render(person),
+    NameStyle::Formal { honorific } => {
+      // Without any title there is nothing formal to say, so fall back to the full name
+      let title = honorific.clone().or_else(|| NameField::Prefix.value(person));
+      match title {
+        Some(title) => {
+          let titled = Person { prefix: Some(title), ..person.clone() };
+          match NameField::Family.value(person) {
+            Some(_) => builtin_templates().formal.render(&titled),
+            None => format_name(&titled, &NameStyle::Full),
+          }
+        },
+        None => format_name(person, &NameStyle::Full),
+      }
+    },
+  }
+}
@@ -0,0 +1,202 @@
+// ======================== INITIALS AND MONOGRAMS ========================
+// The first letter of a name isn't always its first char. What a reader sees as one letter,
+// a grapheme, can be several chars:
+//
+//   > "e" followed by a combining acute accent (U+0301) is one letter, "é"
+//   > Korean written in separate jamo: "ᄒ" "ᅡ" "ᆫ" is the single syllable "한"
+//   > emoji with skin tones or joined with U+200D, and flags made of two regional indicators
+//
+// Taking the first char of "émile" written with a combining accent gives "e" and loses the
+// accent, and slicing the first byte of "Émile" panics because É is two bytes long. So names
+// are split into graphemes first, with the same rules of Unicode's grapheme clusters (UAX #29)
+// as graphemes() in types/chars.rs in 1.starting. The tables are this module's own: every
+// mark in marks.rs stays with the letter before it, and only the main emoji blocks are taken
+// as emoji. A virama joins the consonants around it into one conjunct, so "क्षमा" starts with
+// "क्ष" and not with "क".
+//
+// An initial is the first grapheme that starts with a letter, in titlecase, and each part of
+// a hyphenated name gets one: "Jean-Luc" -> "J.-L.". A monogram is the two letters on an
+// avatar: "JP" for Jean-Luc Picard.
+
+use super::marks::is_mark;
+use super::Person;
+
+// Chars that belong to the grapheme before them: every mark, variation selectors included
+fn extends(c: char) -> bool {
+  is_mark(c)
+    || matches!(c,
+      '\u{200C}' | '\u{200D}'    // zero width non-joiner and joiner
+      | '\u{E33}' | '\u{EB3}'     // Thai and Lao SARA AM, letters that UAX #29 treats as marks
+      | '\u{1F3FB}'..='\u{1F3FF}'  // skin tones
+      | '\u{E0020}'..='\u{E007F}'  // tags, used by the flags of Scotland and Wales
+      | '\u{E0100}'..='\u{E01EF}'  // more variation selectors
+    )
+}
+
+// Signs written before the text they belong to, like the Arabic number sign U+0600
+fn is_prepend(c: char) -> bool {
+  matches!(c,
+    '\u{600}'..='\u{605}' | '\u{6DD}' | '\u{70F}' | '\u{890}' | '\u{891}' | '\u{8E2}' | '\u{D4E}' | '\u{110BD}'
+    | '\u{110CD}' | '\u{111C2}' | '\u{111C3}' | '\u{1193F}' | '\u{11941}' | '\u{11A3A}' | '\u{11A84}'..='\u{11A89}'
+    | '\u{11D46}')
+}
+
+// Line breaks, tabs and the invisible format characters a grapheme never continues across
+fn is_control(c: char) -> bool {
+  c.is_control() || matches!(c, '\u{2028}' | '\u{2029}' | '\u{200B}' | '\u{200E}' | '\u{200F}' | '\u{FEFF}')
+}
+
+// The viramas that join two consonants of Devanagari, Bengali, Gujarati, Oriya, Telugu and Malayalam
+fn is_linker(c: char) -> bool {
+  matches!(c, '\u{94D}' | '\u{9CD}' | '\u{ACD}' | '\u{B4D}' | '\u{C4D}' | '\u{D4D}')
+}
+
+fn is_consonant(c: char) -> bool {
+  c.is_alphabetic() && !extends(c) && !is_prepend(c) && jamo(c).is_none()
+}
+
+// The blocks emoji come from, which a zero width joiner can glue together: 👩‍💻
+fn is_emoji(c: char) -> bool {
+  matches!(c, '\u{2600}'..='\u{27BF}' | '\u{1F000}'..='\u{1F1E5}' | '\u{1F200}'..='\u{1F3FA}' | '\u{1F400}'..='\u{1FAFF}')
+}
+
+fn is_regional_indicator(c: char) -> bool {
+  ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Jamo { Leading, Vowel, Trailing, Syllable, SyllableWithTrailing }
+
+fn jamo(c: char) -> Option<Jamo> {
+  let kind = match c {
+    '\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97F}' => Jamo::Leading,
+    '\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => Jamo::Vowel,
+    '\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => Jamo::Trailing,
+    // Every 28th precomposed syllable has no trailing consonant
+    '\u{AC00}'..='\u{D7A3}' if (c as u32 - 0xAC00).is_multiple_of(28) => Jamo::Syllable,
+    '\u{AC00}'..='\u{D7A3}' => Jamo::SyllableWithTrailing,
+    _ => return None,
+  };
+  Some(kind)
+}
+
+// Whether jamo a and b are parts of one syllable
+fn jamo_joins(a: char, b: char) -> bool {
+  match (jamo(a), jamo(b)) {
+    (Some(Jamo::Leading), Some(_)) => jamo(b) != Some(Jamo::Trailing),
+    (Some(Jamo::Vowel | Jamo::Syllable), Some(Jamo::Vowel | Jamo::Trailing)) => true,
+    (Some(Jamo::Trailing | Jamo::SyllableWithTrailing), Some(Jamo::Trailing)) => true,
+    _ => false,
+  }
+}
+
+pub fn graphemes(text: &str) -> Vec<&str> {
+  let mut clusters = Vec::new();
+  let mut start = 0;
+  let mut previous: Option<char> = None;
+  // Regional indicators pair up: four of them are two flags, not one
+  let mut indicators = 0;
+  // An emoji and its marks, then a zero width joiner, glue to the next emoji
+  let mut in_emoji = false;
+  let mut joiner_after_emoji = false;
+  // A consonant, then a virama (maybe among other marks), glue to the next consonant
+  let mut in_consonants = false;
+  let mut linker_after_consonant = false;
+  for (index, c) in text.char_indices() {
+    let joins = match previous {
+      None => false,
+      Some('\r') => c == '\n',
+      Some(previous) if is_control(previous) || is_control(c) => false,
+      Some(previous) => {
+        extends(c)
+          || is_prepend(previous)
+          || jamo_joins(previous, c)
+          || (joiner_after_emoji && is_emoji(c))
+          || (linker_after_consonant && is_consonant(c))
+          || (is_regional_indicator(previous) && is_regional_indicator(c) && indicators % 2 == 1)
+      },
+    };
+    if !joins && index > 0 {
+      clusters.push(&text[start..index]);
+      start = index;
+    }
+    indicators = if is_regional_indicator(c) { indicators + 1 } else { 0 };
+    joiner_after_emoji = in_emoji && c == '\u{200D}';
+    in_emoji = is_emoji(c) || (in_emoji && extends(c) && c != '\u{200D}');
+    linker_after_consonant = in_consonants && extends(c) && (linker_after_consonant || is_linker(c));
+    in_consonants = is_consonant(c) || (in_consonants && extends(c));
+    previous = Some(c);
+  }
+  if start < text.len() {
+    clusters.push(&text[start..]);
+  }
+  clusters
+}
+
+// How a letter is written at the start of a word. That is its uppercase for most letters, but
+// the digraphs have a capital-then-small form of their own ("ǆ" -> "ǅ", not "Ǆ"), Georgian
+// only uses its capitals for all-caps text, and Greek keeps the iota under the letter.
+// Rust only has to_uppercase, so these few are listed here.
+fn titlecase(c: char) -> char {
+  match c {
+    'Ǆ' | 'ǅ' | 'ǆ' => 'ǅ',
+    'Ǉ' | 'ǈ' | 'ǉ' => 'ǈ',
+    'Ǌ' | 'ǋ' | 'ǌ' => 'ǋ',
+    'Ǳ' | 'ǲ' | 'ǳ' => 'ǲ',
+    '\u{10D0}'..='\u{10FA}' | '\u{10FD}'..='\u{10FF}' => c,
+    // ᾀ -> ᾈ: the titlecase letters are 8 code points after the small ones
+    '\u{1F80}'..='\u{1F87}' | '\u{1F90}'..='\u{1F97}' | '\u{1FA0}'..='\u{1FA7}' => {
+      char::from_u32(c as u32 + 8).unwrap_or(c)
+    },
+    'ᾳ' => 'ᾼ',
+    'ῃ' => 'ῌ',
+    'ῳ' => 'ῼ',
+    _ => {
+      // "ß" would become "SS", two letters for one, so letters like it stay as they are
+      let mut upper = c.to_uppercase();
+      match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => c,
+      }
+    },
+  }
+}
+
+// The first grapheme that starts with a letter, in titlecase: "émile" -> "É"
+pub fn first_letter(word: &str) -> Option<String> {
+  let grapheme = graphemes(word).into_iter().find(|grapheme| grapheme.starts_with(char::is_alphabetic))?;
+  let mut chars = grapheme.chars();
+  let base = titlecase(chars.next()?);
+  Some(std::iter::once(base).chain(chars).collect())
+}
+
+// "Oliver" -> "O.", "Jean-Luc" -> "J.-L.", "O'Brien" -> "O."
+pub fn initial(word: &str) -> String {
+  let parts: Vec<String> =
+    word.split(['-', '\u{2010}']).filter_map(first_letter).map(|letter| format!("{}.", letter)).collect();
+  parts.join("-")
+}
+
+// The initials of every given and family name, particles and titles left out: "J.O.S."
+pub fn initials(person: &Person) -> String {
+  let names = person.given.iter().chain(&person.family);
+  names.flat_map(|name| name.split_whitespace()).map(initial).collect()
+}
+
+// Two letters for an avatar: the first letters of the name someone is called by and of their
+// family name, or the first two letters of the only name there is: "Cher" -> "CH"
+pub fn monogram(person: &Person) -> String {
+  let called = person.called().and_then(first_letter);
+  let family = person.family.iter().find_map(|name| first_letter(name));
+  match (called, family) {
+    (Some(called), Some(family)) => called + &family,
+    (Some(_), None) => two_letters(person.called().unwrap_or_default()),
+    (None, Some(_)) => two_letters(&person.family.join(" ")),
+    (None, None) => String::new(),
+  }
+}
+
+fn two_letters(name: &str) -> String {
+  let letters = graphemes(name).into_iter().filter(|grapheme| grapheme.starts_with(char::is_alphabetic));
+  letters.take(2).filter_map(first_letter).collect()
+}
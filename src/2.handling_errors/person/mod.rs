@@ -0,0 +1,134 @@
+// ======================== PERSON ========================
+// exercise1.rs builds "James Oliver Smith" out of a Person with an optional middle name.
+// This module is the shared version of that Person, grown to hold the other parts real names
+// have, so every exercise that deals with names agrees on what a name looks like:
+//
+//   > prefix: a title in front of the name, like "Dr." or "Prof."
+//   > given names: the first name and any number of middle names ("Mary Ann Jane")
+//   > particle: small words that belong to the family name, like "van" in Ludwig van Beethoven
+//   > family names: one or more, like the paternal and maternal surnames in "García López"
+//   > suffix: what comes after the name, like "Jr." or "PhD"
+//   > nickname ("Jim") and preferred name (the name someone asks to be called by)
+//
+// Person::new still takes the three parts exercise1.rs started with.
+//
+// Several binaries include this module and each one uses a different part of it.
+#![allow(dead_code)]
+
+pub mod collate;
+pub mod dedup;
+pub mod directory;
+pub mod format;
+pub mod initials;
+pub mod marks;
+pub mod parse;
+pub mod validate;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Person {
+  pub prefix: Option<String>,
+  pub given: Vec<String>,
+  pub particle: Option<String>,
+  pub family: Vec<String>,
+  pub suffix: Option<String>,
+  pub nickname: Option<String>,
+  pub preferred: Option<String>,
+}
+
+impl Person {
+  // A middle like "Mary Ann" becomes two middle names
+  pub fn new(first: &str, middle: Option<&str>, last: &str) -> Person {
+    let mut given = vec![String::from(first)];
+    given.extend(middle.unwrap_or_default().split_whitespace().map(String::from));
+    Person { given, family: vec![String::from(last)], ..Person::default() }
+  }
+
+  // Person::new, but every part is checked first and all problems are reported together
+  pub fn try_new(first: &str, middle: Option<&str>, last: &str) -> Result<Person, validate::ValidationError> {
+    validate::Validator::default().person(first, middle, last)
+  }
+
+  pub fn from_names(given: &[&str], family: &[&str]) -> Person {
+    Person {
+      given: given.iter().map(|name| name.to_string()).collect(),
+      family: family.iter().map(|name| name.to_string()).collect(),
+      ..Person::default()
+    }
+  }
+
+  pub fn with_prefix(mut self, prefix: &str) -> Person {
+    self.prefix = Some(String::from(prefix));
+    self
+  }
+
+  pub fn with_particle(mut self, particle: &str) -> Person {
+    self.particle = Some(String::from(particle));
+    self
+  }
+
+  pub fn with_suffix(mut self, suffix: &str) -> Person {
+    self.suffix = Some(String::from(suffix));
+    self
+  }
+
+  pub fn with_nickname(mut self, nickname: &str) -> Person {
+    self.nickname = Some(String::from(nickname));
+    self
+  }
+
+  pub fn with_preferred(mut self, preferred: &str) -> Person {
+    self.preferred = Some(String::from(preferred));
+    self
+  }
+
+  pub fn first(&self) -> Option<&str> {
+    self.given.first().map(String::as_str).filter(|name| !name.is_empty())
+  }
+
+  pub fn middle_names(&self) -> &[String] {
+    self.given.get(1..).unwrap_or_default()
+  }
+
+  // All middle names in one string, the way exercise1.rs had it
+  pub fn middle(&self) -> Option<String> {
+    joined(self.middle_names())
+  }
+
+  // The family names without the particle: "García López"
+  pub fn last(&self) -> Option<String> {
+    joined(&self.family)
+  }
+
+  // "van Beethoven": the family names together with their particle
+  pub fn family_name(&self) -> Option<String> {
+    let parts: Vec<String> = self.particle.iter().cloned().chain(self.last()).collect();
+    joined(&parts)
+  }
+
+  // The name to talk to someone with: what they prefer, or else their first name
+  pub fn called(&self) -> Option<&str> {
+    self.preferred.as_deref().filter(|name| !name.is_empty()).or(self.first())
+  }
+
+  // "Bill Gates" for William Henry Gates III who prefers Bill
+  pub fn display_name(&self) -> String {
+    let parts: Vec<String> = self.called().map(String::from).into_iter().chain(self.family_name()).collect();
+    parts.join(" ")
+  }
+}
+
+// The non-empty names separated by single spaces, None if there are none
+fn joined(names: &[String]) -> Option<String> {
+  let names: Vec<&str> = names.iter().map(|name| name.trim()).filter(|name| !name.is_empty()).collect();
+  if names.is_empty() {
+    None
+  } else {
+    Some(names.join(" "))
+  }
+}
+
+// Every part that is there, in the usual order, separated by single spaces.
+// Nicknames and preferred names are left out: this is the name on official papers.
+pub fn build_full_name(person: &Person) -> String {
+  format::format_name(person, &format::NameStyle::Full)
+}
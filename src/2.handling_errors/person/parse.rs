@@ -0,0 +1,315 @@
+// ======================== PARSING NAMES ========================
+// build_full_name turns a Person into text. parse_name goes the other way:
+//
+//   "James Oliver Smith"      -> first James, middle Oliver, last Smith
+//   "Smith, James O."         -> the same order written family name first, with a comma
+//   "Dr. Alice Stevens Jr."   -> prefix Dr., first Alice, last Stevens, suffix Jr.
+//   "Ludwig van Beethoven"    -> first Ludwig, particle van, last Beethoven
+//   "García López, María"     -> first María, two family names García and López
+//   "James "Jim" Smith"       -> a nickname in quotes or (brackets) anywhere in the name
+//
+// Names don't follow strict rules, so some text can be read more than one way. Instead of
+// silently picking one, parse_name says what it chose and why it wasn't sure, as a list of
+// ambiguities and an overall confidence. Text that can't be a name at all is an error.
+
+use std::fmt;
+
+use super::format::NameField;
+use super::marks::is_mark;
+use super::{build_full_name, Person};
+
+const PREFIXES: [&str; 19] = [
+  "mr", "mrs", "ms", "miss", "mx", "dr", "prof", "sir", "dame", "lady", "lord", "rev", "fr", "hon", "capt", "col",
+  "gen", "lt", "sgt",
+];
+
+// A lone "V" is far more often an initial than "the fifth", so it isn't listed
+const SUFFIXES: [&str; 18] = [
+  "jr", "sr", "ii", "iii", "iv", "phd", "md", "dds", "esq", "mba", "ba", "bsc", "msc", "obe", "mbe", "cbe", "kc", "qc",
+];
+
+const PARTICLES: [&str; 23] = [
+  "van", "von", "der", "den", "de", "del", "della", "dalla", "di", "da", "du", "des", "la", "le", "ter", "ten", "bin",
+  "ibn", "al", "el", "dos", "das", "do",
+];
+
+// "Dr." and "dr" are the same word
+fn bare(word: &str) -> String {
+  word.trim_end_matches('.').to_lowercase()
+}
+
+fn is_prefix(word: &str) -> bool {
+  PREFIXES.contains(&bare(word).as_str())
+}
+
+fn is_suffix(word: &str) -> bool {
+  SUFFIXES.contains(&bare(word).as_str())
+}
+
+fn is_particle(word: &str) -> bool {
+  PARTICLES.contains(&bare(word).as_str())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamePart { First, Last }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ambiguity {
+  // Only one word was left once titles were removed
+  SingleName { word: String, read_as: NamePart },
+  // "Maria Jose Garcia Lopez": the last middle name may really be a second family name
+  SeveralMiddleNames { middle: String },
+  // "Vincent Van Gogh": a capitalised particle may be a middle name instead
+  CapitalisedParticle { word: String },
+}
+
+impl fmt::Display for Ambiguity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Ambiguity::SingleName { word, read_as: NamePart::First } => {
+        write!(f, "'{}' is the only name given, read as a first name", word)
+      },
+      Ambiguity::SingleName { word, read_as: NamePart::Last } => {
+        write!(f, "'{}' is the only name given, read as a family name because it follows a title", word)
+      },
+      Ambiguity::SeveralMiddleNames { middle } => {
+        write!(f, "'{}' were read as middle names, the last of them may be a second family name", middle)
+      },
+      Ambiguity::CapitalisedParticle { word } => {
+        write!(f, "'{}' was read as part of the family name, but capitalised it could be a middle name", word)
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence { Low, Medium, High }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameParseError {
+  Empty,
+  InvalidCharacter { character: char, position: usize },
+  // "Dr. Jr.": nothing but titles and suffixes
+  OnlyTitles { text: String },
+  MissingGivenName { text: String },
+  MissingFamilyName { text: String },
+  UnclosedNickname { position: usize },
+  // A third comma separated part that isn't a suffix, as in "Smith, James, Oliver"
+  UnexpectedSegment { segment: String },
+}
+
+impl fmt::Display for NameParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      NameParseError::Empty => write!(f, "the name is empty"),
+      NameParseError::InvalidCharacter { character, position } => {
+        write!(f, "{:?} at position {} can't be part of a name", character, position)
+      },
+      NameParseError::OnlyTitles { text } => write!(f, "'{}' has titles but no name", text),
+      NameParseError::MissingGivenName { text } => write!(f, "'{}' has no given name after the comma", text),
+      NameParseError::MissingFamilyName { text } => write!(f, "'{}' has no family name before the comma", text),
+      NameParseError::UnclosedNickname { position } => {
+        write!(f, "the nickname starting at position {} is never closed", position)
+      },
+      NameParseError::UnexpectedSegment { segment } => {
+        write!(f, "'{}' isn't a suffix, expected 'Family, Given' or 'Family, Given, Suffix'", segment)
+      },
+    }
+  }
+}
+
+impl std::error::Error for NameParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedName {
+  pub input: String,
+  pub person: Person,
+  pub ambiguities: Vec<Ambiguity>,
+}
+
+impl ParsedName {
+  pub fn confidence(&self) -> Confidence {
+    let single = self.ambiguities.iter().any(|ambiguity| matches!(ambiguity, Ambiguity::SingleName { .. }));
+    match (single, self.ambiguities.is_empty()) {
+      (true, _) => Confidence::Low,
+      (false, false) => Confidence::Medium,
+      (false, true) => Confidence::High,
+    }
+  }
+}
+
+impl fmt::Display for ParsedName {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "{} -> {} ({:?} confidence)", self.input, build_full_name(&self.person), self.confidence())?;
+    let fields = [
+      NameField::Prefix, NameField::First, NameField::Middle, NameField::Particle, NameField::Last, NameField::Suffix,
+      NameField::Nickname,
+    ];
+    for field in fields {
+      if let Some(part) = field.value(&self.person) {
+        writeln!(f, "  {:<9} {}", field.name(), part)?;
+      }
+    }
+    for ambiguity in &self.ambiguities {
+      writeln!(f, "  ? {}", ambiguity)?;
+    }
+    Ok(())
+  }
+}
+
+// Remove titles from the front and suffixes from the back, always leaving at least one word
+fn take_titles<'a>(words: &mut Vec<&'a str>) -> (Vec<&'a str>, Vec<&'a str>) {
+  let prefix_count = words.iter().take_while(|word| is_prefix(word)).count().min(words.len().saturating_sub(1));
+  let prefixes: Vec<&str> = words.drain(..prefix_count).collect();
+  let suffix_count = words.iter().rev().take_while(|word| is_suffix(word)).count().min(words.len().saturating_sub(1));
+  let suffixes: Vec<&str> = words.drain(words.len() - suffix_count..).collect();
+  (prefixes, suffixes)
+}
+
+fn owned(words: &[&str]) -> Vec<String> {
+  words.iter().map(|word| word.to_string()).collect()
+}
+
+fn joined(words: &[&str]) -> Option<String> {
+  if words.is_empty() {
+    None
+  } else {
+    Some(words.join(" "))
+  }
+}
+
+fn note_capitalised_particles(particles: &[&str], ambiguities: &mut Vec<Ambiguity>) {
+  for word in particles {
+    if word.chars().next().is_some_and(char::is_uppercase) {
+      ambiguities.push(Ambiguity::CapitalisedParticle { word: word.to_string() });
+    }
+  }
+}
+
+// Take the first "quoted" or (bracketed) part out of the text as the nickname
+fn take_nickname(text: &str) -> Result<(String, Option<String>), NameParseError> {
+  let (start, opening) = match text.char_indices().find(|(_, c)| "\"“(".contains(*c)) {
+    Some(found) => found,
+    None => return Ok((text.to_string(), None)),
+  };
+  let closing = match opening {
+    '“' => '”',
+    '(' => ')',
+    _ => '"',
+  };
+  let inside_start = start + opening.len_utf8();
+  let position = text[..start].chars().count();
+  let length = text[inside_start..].find(closing).ok_or(NameParseError::UnclosedNickname { position })?;
+  let nickname = text[inside_start..inside_start + length].trim();
+  let rest = format!("{} {}", &text[..start], &text[inside_start + length + closing.len_utf8()..]);
+  let nickname = if nickname.is_empty() { None } else { Some(nickname.to_string()) };
+  Ok((rest, nickname))
+}
+
+pub fn parse_name(text: &str) -> Result<ParsedName, NameParseError> {
+  for (position, character) in text.chars().enumerate() {
+    // Accents and vowel signs may come as separate marks after their letter
+    let letter = character.is_alphabetic() || is_mark(character);
+    if !(letter || character.is_whitespace() || ".,-'’\"“”()".contains(character)) {
+      return Err(NameParseError::InvalidCharacter { character, position });
+    }
+  }
+  let input = text.split_whitespace().collect::<Vec<_>>().join(" ");
+  if input.is_empty() {
+    return Err(NameParseError::Empty);
+  }
+  let (name, nickname) = take_nickname(&input)?;
+  let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+
+  // Trailing comma parts that are all suffixes: "James Smith, Jr." or "Smith, James, PhD"
+  let mut segments: Vec<&str> = name.split(',').map(str::trim).collect();
+  let mut comma_suffixes = Vec::new();
+  while segments.len() > 1 {
+    let last = segments[segments.len() - 1];
+    if last.is_empty() || !last.split_whitespace().all(is_suffix) {
+      break;
+    }
+    comma_suffixes.insert(0, last);
+    segments.pop();
+  }
+
+  let mut ambiguities = Vec::new();
+  let mut person = match segments.as_slice() {
+    [name] => parse_given_first(&input, name, &mut ambiguities)?,
+    [family, given] => parse_family_first(&input, family, given, &mut ambiguities)?,
+    [_, _, extra, ..] => return Err(NameParseError::UnexpectedSegment { segment: extra.to_string() }),
+    [] => return Err(NameParseError::Empty),
+  };
+  person.nickname = nickname;
+
+  if !comma_suffixes.is_empty() {
+    let mut suffixes: Vec<&str> = person.suffix.iter().map(String::as_str).collect();
+    suffixes.extend(comma_suffixes);
+    person.suffix = Some(suffixes.join(", "));
+  }
+  Ok(ParsedName { input, person, ambiguities })
+}
+
+// "Dr. Ludwig van Beethoven Jr."
+fn parse_given_first(input: &str, name: &str, ambiguities: &mut Vec<Ambiguity>) -> Result<Person, NameParseError> {
+  let mut words: Vec<&str> = name.split_whitespace().collect();
+  let (prefixes, suffixes) = take_titles(&mut words);
+  let mut person = Person { prefix: joined(&prefixes), suffix: joined(&suffixes), ..Person::default() };
+
+  match words.as_slice() {
+    [] => return Err(NameParseError::Empty),
+    [word] if is_prefix(word) || is_suffix(word) => return Err(NameParseError::OnlyTitles { text: input.to_string() }),
+    // "Dr. Smith" is about a family name, "Madonna" about a first name
+    [word] if person.prefix.is_some() => {
+      person.family = vec![word.to_string()];
+      ambiguities.push(Ambiguity::SingleName { word: word.to_string(), read_as: NamePart::Last });
+    },
+    [word] => {
+      person.given = vec![word.to_string()];
+      ambiguities.push(Ambiguity::SingleName { word: word.to_string(), read_as: NamePart::First });
+    },
+    [first, rest @ .., last] => {
+      // The particles are the run of small words right before the last name
+      let particle_count = rest.iter().rev().take_while(|word| is_particle(word)).count();
+      let (middle, particles) = rest.split_at(rest.len() - particle_count);
+      person.given = owned(&[&[*first], middle].concat());
+      person.particle = joined(particles);
+      person.family = vec![last.to_string()];
+      if middle.len() > 1 {
+        ambiguities.push(Ambiguity::SeveralMiddleNames { middle: middle.join(" ") });
+      }
+      note_capitalised_particles(particles, ambiguities);
+    },
+  }
+  Ok(person)
+}
+
+// "van Beethoven, Ludwig" and "Smith, Dr. James O."
+fn parse_family_first(input: &str, family: &str, given: &str, ambiguities: &mut Vec<Ambiguity>) -> Result<Person, NameParseError> {
+  let family_words: Vec<&str> = family.split_whitespace().collect();
+  if family_words.is_empty() {
+    return Err(NameParseError::MissingFamilyName { text: input.to_string() });
+  }
+  let mut given_words: Vec<&str> = given.split_whitespace().collect();
+  if given_words.is_empty() {
+    return Err(NameParseError::MissingGivenName { text: input.to_string() });
+  }
+  let (prefixes, suffixes) = take_titles(&mut given_words);
+  if given_words.iter().all(|word| is_prefix(word) || is_suffix(word)) {
+    return Err(NameParseError::MissingGivenName { text: input.to_string() });
+  }
+
+  // The comma already says where the family names are, so only the particles need finding
+  let particle_count = family_words.iter().take_while(|word| is_particle(word)).count().min(family_words.len() - 1);
+  let (particles, family) = family_words.split_at(particle_count);
+  note_capitalised_particles(particles, ambiguities);
+
+  Ok(Person {
+    prefix: joined(&prefixes),
+    given: owned(&given_words),
+    particle: joined(particles),
+    family: owned(family),
+    suffix: joined(&suffixes),
+    ..Person::default()
+  })
+}
@@ -0,0 +1,299 @@
+// ======================== VALIDATING PEOPLE ========================
+// Person takes any String, so nothing stops an empty first name, a name with a tab in it or
+// "Smith " with a space at the end, and the mistake only shows up later in a letter or a
+// sorted list. A Validator checks a Person and lists every problem at once, so a form can
+// show them all instead of one per try:
+//
+//   > a missing or empty first name or family name
+//   > an optional part that is there but empty, like Some("") for a nickname or an empty
+//     middle name, which should just be missing
+//   > spaces at either end, or several in a row
+//   > characters that don't belong in names: digits, symbols, control characters
+//   > a part longer than max_length characters
+//
+// It can also clean the person up first, and then checks what is left:
+//
+//   > trimming: spaces at the ends removed, runs of spaces made one, empty optional parts
+//     removed
+//   > composing: letters written as a letter and a combining accent ("e" and U+0301) made
+//     into the single accented letter ("é"), which is what Unicode NFC does. Without this
+//     two names that look the same compare as different. Only the Latin letters up to U+024F
+//     are composed here; others are left as they are.
+//
+// Person::try_new is Person::new with the default Validator, which only checks.
+
+use std::fmt;
+
+use super::marks::is_mark;
+use super::Person;
+
+const COMPOSITIONS: [(char, &str, &str); 17] = [
+  ('\u{300}', "AEIOUaeiouÜüNn", "ÀÈÌÒÙàèìòùǛǜǸǹ"),
+  ('\u{301}', "AEIOUYaeiouyCcLlNnRrSsZzÜüGgÅåÆæØø", "ÁÉÍÓÚÝáéíóúýĆćĹĺŃńŔŕŚśŹźǗǘǴǵǺǻǼǽǾǿ"),
+  ('\u{302}', "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ"),
+  ('\u{303}', "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"),
+  ('\u{304}', "AaEeIiOoUuÜüÄäȦȧÆæǪǫÖöÕõȮȯYy", "ĀāĒēĪīŌōŪūǕǖǞǟǠǡǢǣǬǭȪȫȬȭȰȱȲȳ"),
+  ('\u{306}', "AaEeGgIiOoUu", "ĂăĔĕĞğĬĭŎŏŬŭ"),
+  ('\u{307}', "CcEeGgIZzAaOo", "ĊċĖėĠġİŻżȦȧȮȯ"),
+  ('\u{308}', "AEIOUaeiouyY", "ÄËÏÖÜäëïöüÿŸ"),
+  ('\u{30a}', "AaUu", "ÅåŮů"),
+  ('\u{30b}', "OoUu", "ŐőŰű"),
+  ('\u{30c}', "CcDdEeLlNnRrSsTtZzAaIiOoUuÜüGgKkƷʒjHh", "ČčĎďĚěĽľŇňŘřŠšŤťŽžǍǎǏǐǑǒǓǔǙǚǦǧǨǩǮǯǰȞȟ"),
+  ('\u{30f}', "AaEeIiOoRrUu", "ȀȁȄȅȈȉȌȍȐȑȔȕ"),
+  ('\u{311}', "AaEeIiOoRrUu", "ȂȃȆȇȊȋȎȏȒȓȖȗ"),
+  ('\u{31b}', "OoUu", "ƠơƯư"),
+  ('\u{326}', "SsTt", "ȘșȚț"),
+  ('\u{327}', "CcGgKkLlNnRrSsTtEe", "ÇçĢģĶķĻļŅņŖŗŞşŢţȨȩ"),
+  ('\u{328}', "AaEeIiUuOoŌō", "ĄąĘęĮįŲųǪǫǬǭ"),
+];
+
+// Combines every letter with the accents after it when there is a single letter for both
+pub fn compose(text: &str) -> String {
+  let mut composed = String::new();
+  for c in text.chars() {
+    let combined = composed.chars().last().and_then(|last| {
+      let (_, bases, letters) = COMPOSITIONS.iter().find(|(mark, _, _)| *mark == c)?;
+      let index = bases.chars().position(|base| base == last)?;
+      letters.chars().nth(index).map(|letter| (last, letter))
+    });
+    match combined {
+      Some((last, letter)) => {
+        composed.truncate(composed.len() - last.len_utf8());
+        composed.push(letter);
+      },
+      None => composed.push(c),
+    }
+  }
+  composed
+}
+
+// The other way around: a composed letter as its letter and accent, "é" -> ('e', U+0301).
+// The letter may have an accent of its own ("ǘ" is "ü" and U+0301), decompose it again for that.
+pub fn decompose(c: char) -> Option<(char, char)> {
+  COMPOSITIONS.iter().find_map(|(mark, bases, letters)| {
+    let index = letters.chars().position(|letter| letter == c)?;
+    bases.chars().nth(index).map(|base| (base, *mark))
+  })
+}
+
+// Spaces trimmed from the ends and every run of whitespace made a single space
+fn tidy(text: &str) -> String {
+  text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Where in the Person a problem is. Given and family names are indexes into person.given and
+// person.family, counting from 0; Display names them for the user: Given(2) is "middle name 2".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersonField {
+  Prefix,
+  Given(usize),
+  Particle,
+  Family(usize),
+  Suffix,
+  Nickname,
+  Preferred,
+}
+
+impl fmt::Display for PersonField {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PersonField::Prefix => write!(f, "prefix"),
+      PersonField::Given(0) => write!(f, "first name"),
+      PersonField::Given(1) => write!(f, "middle name"),
+      PersonField::Given(index) => write!(f, "middle name {}", index),
+      PersonField::Particle => write!(f, "particle"),
+      PersonField::Family(0) => write!(f, "family name"),
+      PersonField::Family(index) => write!(f, "family name {}", index + 1),
+      PersonField::Suffix => write!(f, "suffix"),
+      PersonField::Nickname => write!(f, "nickname"),
+      PersonField::Preferred => write!(f, "preferred name"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+  // The first name and the family name have to be there
+  Missing { field: PersonField },
+  // Anything else that is there but empty should have been left out
+  Empty { field: PersonField },
+  Whitespace { field: PersonField, value: String },
+  DisallowedCharacter { field: PersonField, character: char, position: usize },
+  TooLong { field: PersonField, length: usize, max: usize },
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Problem::Missing { field } => write!(f, "the {} is missing", field),
+      Problem::Empty { field } => write!(f, "the {} is empty, leave it out instead", field),
+      Problem::Whitespace { field, value } => {
+        write!(f, "the {} {:?} has spaces at the ends or several in a row", field, value)
+      },
+      Problem::DisallowedCharacter { field, character, position } => {
+        write!(f, "the {} has {:?} at position {}, which can't be part of a name", field, character, position)
+      },
+      Problem::TooLong { field, length, max } => {
+        write!(f, "the {} is {} characters long, at most {} are allowed", field, length, max)
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+  pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let problems: Vec<String> = self.problems.iter().map(|problem| problem.to_string()).collect();
+    write!(f, "{}", problems.join("; "))
+  }
+}
+
+impl std::error::Error for ValidationError {}
+
+// Letters, marks, spaces and the punctuation names really have: "O'Brien-Smith", "Jr.".
+// The zero width joiner and non-joiner choose how letters join in scripts like Devanagari
+// and Persian, so they are part of how some names are spelled.
+fn allowed(c: char) -> bool {
+  c.is_alphabetic() || is_mark(c) || matches!(c, '\u{200C}' | '\u{200D}') || c == ' ' || ".-'’".contains(c)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validator {
+  pub max_length: usize,
+  pub trim: bool,
+  pub compose: bool,
+}
+
+impl Default for Validator {
+  fn default() -> Validator {
+    Validator { max_length: 100, trim: false, compose: false }
+  }
+}
+
+impl Validator {
+  pub fn new() -> Validator {
+    Validator::default()
+  }
+
+  pub fn trimming(mut self) -> Validator {
+    self.trim = true;
+    self
+  }
+
+  pub fn composing(mut self) -> Validator {
+    self.compose = true;
+    self
+  }
+
+  pub fn with_max_length(mut self, max_length: usize) -> Validator {
+    self.max_length = max_length;
+    self
+  }
+
+  fn normalise(&self, value: &str) -> String {
+    let value = if self.trim { tidy(value) } else { value.to_string() };
+    if self.compose { compose(&value) } else { value }
+  }
+
+  // The person cleaned up as the options say, when nothing is wrong with it
+  pub fn validate(&self, person: Person) -> Result<Person, ValidationError> {
+    let optional = |value: Option<String>| {
+      let value = value.map(|value| self.normalise(&value));
+      if self.trim { value.filter(|value| !value.is_empty()) } else { value }
+    };
+    let mut given: Vec<String> = person.given.iter().map(|name| self.normalise(name)).collect();
+    let mut family: Vec<String> = person.family.iter().map(|name| self.normalise(name)).collect();
+    if self.trim {
+      // The first name keeps its place even when empty, so it is reported as missing
+      let mut index = 0;
+      given.retain(|name| {
+        index += 1;
+        index == 1 || !name.is_empty()
+      });
+      family.retain(|name| !name.is_empty());
+    }
+    let person = Person {
+      prefix: optional(person.prefix),
+      given,
+      particle: optional(person.particle),
+      family,
+      suffix: optional(person.suffix),
+      nickname: optional(person.nickname),
+      preferred: optional(person.preferred),
+    };
+
+    let mut problems = Vec::new();
+    let first_missing = person.given.first().is_none_or(|first| first.is_empty());
+    let family_missing = person.family.iter().all(|family| family.is_empty());
+    if first_missing {
+      problems.push(Problem::Missing { field: PersonField::Given(0) });
+    }
+    if family_missing {
+      problems.push(Problem::Missing { field: PersonField::Family(0) });
+    }
+    for (field, value) in fields(&person) {
+      if value.is_empty() {
+        // An empty first or only family name was just reported as missing
+        let reported = match field {
+          PersonField::Given(0) => first_missing,
+          PersonField::Family(_) => family_missing,
+          _ => false,
+        };
+        if !reported {
+          problems.push(Problem::Empty { field });
+        }
+      } else {
+        self.check(field, value, &mut problems);
+      }
+    }
+
+    if problems.is_empty() {
+      Ok(person)
+    } else {
+      Err(ValidationError { problems })
+    }
+  }
+
+  fn check(&self, field: PersonField, value: &str, problems: &mut Vec<Problem>) {
+    // Only spaces here, a tab or a line break anywhere is a disallowed character below
+    if value.starts_with(' ') || value.ends_with(' ') || value.contains("  ") {
+      problems.push(Problem::Whitespace { field, value: value.to_string() });
+    }
+    // Spaces are fine, tabs and line breaks are not
+    for (position, character) in value.chars().enumerate() {
+      if !allowed(character) {
+        problems.push(Problem::DisallowedCharacter { field, character, position });
+      }
+    }
+    let length = value.chars().count();
+    if length > self.max_length {
+      problems.push(Problem::TooLong { field, length, max: self.max_length });
+    }
+  }
+
+  // Person::new with every part checked; Some("") for the middle name is a problem here
+  pub fn person(&self, first: &str, middle: Option<&str>, last: &str) -> Result<Person, ValidationError> {
+    let given = std::iter::once(first).chain(middle).map(String::from).collect();
+    let person = self.validate(Person { given, family: vec![String::from(last)], ..Person::default() })?;
+    // The middle was checked as one piece, now each of its words becomes a middle name
+    let middle = person.middle();
+    Ok(Person::new(person.first().unwrap_or_default(), middle.as_deref(), person.last().unwrap_or_default().as_str()))
+  }
+}
+
+// Every part of the person that has a value, empty ones included
+fn fields(person: &Person) -> Vec<(PersonField, &str)> {
+  let mut fields = Vec::new();
+  fields.extend(person.prefix.as_deref().map(|value| (PersonField::Prefix, value)));
+  fields.extend(person.given.iter().enumerate().map(|(index, name)| (PersonField::Given(index), name.as_str())));
+  fields.extend(person.particle.as_deref().map(|value| (PersonField::Particle, value)));
+  fields.extend(person.family.iter().enumerate().map(|(index, name)| (PersonField::Family(index), name.as_str())));
+  fields.extend(person.suffix.as_deref().map(|value| (PersonField::Suffix, value)));
+  fields.extend(person.nickname.as_deref().map(|value| (PersonField::Nickname, value)));
+  fields.extend(person.preferred.as_deref().map(|value| (PersonField::Preferred, value)));
+  fields
+}